@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+/// Comments to write above the values of a document, keyed by their path.
+///
+/// Paths are written the same way as in a `SourceMap`, e.g. `keys.49[2]`.
+/// The comment of the empty path is written at the top of the document.
+/// A comment spanning several lines is written as several `#` lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommentMap {
+    comments: BTreeMap<String, String>,
+}
+
+impl CommentMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the comment of a path, returning the one it replaces.
+    pub fn insert(&mut self, path: impl Into<String>, comment: impl Into<String>) -> Option<String> {
+        self.comments.insert(path.into(), comment.into())
+    }
+
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.comments.get(path).map(String::as_str)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        self.comments.remove(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.comments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, &str)> {
+        self.comments.iter().map(|(path, comment)| (path.as_str(), comment.as_str()))
+    }
+}
+
+impl<P: Into<String>, C: Into<String>> FromIterator<(P, C)> for CommentMap {
+    fn from_iter<I: IntoIterator<Item = (P, C)>>(iter: I) -> Self {
+        CommentMap {
+            comments: iter.into_iter().map(|(path, comment)| (path.into(), comment.into())).collect(),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::Deserializer;
+use crate::error::{ Error, SpannedError };
+use crate::parse::{ is_ident_first_char, is_ident_other_char };
+use crate::source_map::PathSegment;
+use crate::value::{ Map, Value };
+use serde::Deserialize;
+
+/// Reads a document as a `Value` without stopping at the first error.
+///
+/// After an error in an entry of a list, tuple or map, the rest of the entry
+/// is skipped up to the next comma or closing bracket and reading goes on,
+/// so every error of the document is reported at once. The value is what
+/// could be read around the errors, or `None` if nothing could.
+pub fn check(source: &str) -> (Option<Value>, Vec<SpannedError>) {
+    let mut checker = match Deserializer::from_str(source) {
+        Ok(de) => Checker { de, errors: Vec::new() },
+        Err(e) => return (None, vec![e]),
+    };
+
+    let value = checker.value();
+
+    if value.is_some() {
+        if let Err(e) = checker.de.end() {
+            checker.error(e);
+        }
+    }
+
+    (value, checker.errors)
+}
+
+struct Checker<'de> {
+    de: Deserializer<'de>,
+    errors: Vec<SpannedError>,
+}
+
+impl<'de> Checker<'de> {
+    fn error(&mut self, e: Error) {
+        self.errors.push(self.de.span_error(e));
+        self.de.value_span = None;
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        if let Err(e) = self.de.bytes.skip_ws() {
+            self.error(e);
+            return None;
+        }
+
+        let mut bytes = self.de.bytes;
+
+        if bytes.consume_ident("Some") {
+            let _ = bytes.skip_ws();
+
+            if bytes.peek() == Some(b'(') {
+                self.de.bytes = bytes;
+                return self.some();
+            }
+        }
+
+        // Struct names are skipped, as a `Value` has no room for them
+        let mut bytes = self.de.bytes;
+
+        if bytes.identifier().is_ok() && { let _ = bytes.skip_ws(); matches!(bytes.peek(), Some(b'(' | b'{')) } {
+            self.de.bytes = bytes;
+        }
+
+        match self.de.bytes.peek() {
+            Some(b'[') => self.entries("[", "]", false),
+            Some(b'(') if !self.de.bytes.bytes().starts_with(b"()") => self.entries("(", ")", false),
+            Some(b'{') if !self.de.bytes.bytes().starts_with(b"{}") => self.entries("{", "}", true),
+            _ => self.scalar(),
+        }
+    }
+
+    fn some(&mut self) -> Option<Value> {
+        let openings = self.de.openings.len();
+
+        if let Err(e) = self.de.open("(") {
+            self.error(e);
+            return None;
+        }
+
+        let value = self.value();
+        if value.is_none() {
+            self.skip_entry();
+        }
+
+        let closed = self.de.bytes.skip_ws().is_ok() && self.de.close(")");
+
+        if !closed && value.is_some() {
+            self.error(Error::ExpectedOptionEnd);
+        }
+
+        self.de.openings.truncate(openings);
+
+        value.filter(|_| closed).map(|value| Value::Option(Some(Box::new(value))))
+    }
+
+    fn scalar(&mut self) -> Option<Value> {
+        let (path, openings, names) = (self.de.path.len(), self.de.openings.len(), self.de.names.len());
+
+        match Value::deserialize(&mut self.de) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error(e);
+                self.de.path.truncate(path);
+                self.de.openings.truncate(openings);
+                self.de.names.truncate(names);
+                None
+            }
+        }
+    }
+
+    /// Reads the entries of a list, tuple or map, skipping those with errors.
+    fn entries(&mut self, open: &str, close: &str, is_map: bool) -> Option<Value> {
+        let mut seq = Vec::new();
+        let mut map = Map::new();
+        let openings = self.de.openings.len();
+
+        if let Err(e) = self.de.open(open) {
+            self.error(e);
+            return None;
+        }
+
+        loop {
+            if let Err(e) = self.de.bytes.skip_ws() {
+                self.error(e);
+                break;
+            }
+
+            if self.de.close(close) {
+                break;
+            }
+
+            match self.de.bytes.peek() {
+                None => {
+                    // Only the innermost construct left open is reported
+                    if !self.errors.last().is_some_and(|e| e.code == Error::Eof) {
+                        self.error(Error::Eof);
+                    }
+                    break;
+                }
+                // A stray closing bracket is skipped, with its comma
+                Some(b')' | b']' | b'}') => {
+                    self.error(if is_map { Error::ExpectedMapEnd } else { Error::ExpectedArrayEnd });
+                    let _ = self.de.bytes.advance_single();
+                    let _ = self.de.bytes.skip_ws().and_then(|_| self.de.bytes.comma());
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            let complete = if is_map {
+                self.map_entry(&mut map)
+            } else {
+                self.de.path.push(PathSegment::Index(seq.len()));
+                let element = self.value();
+                self.de.path.pop();
+                element.map(|element| seq.push(element)).is_some()
+            };
+
+            if !complete {
+                self.skip_entry();
+            }
+
+            match self.de.bytes.comma() {
+                Ok(false) if !matches!(self.de.bytes.peek(), None | Some(b')' | b']' | b'}')) => {
+                    self.error(Error::ExpectedComma);
+                }
+                Ok(_) => {}
+                Err(e) => self.error(e),
+            }
+        }
+
+        self.de.openings.truncate(openings);
+
+        Some(if is_map { Value::Map(map) } else { Value::Seq(seq) })
+    }
+
+    fn map_entry(&mut self, map: &mut Map) -> bool {
+        let cavetta = self.de.bytes.consume("<");
+        let before = self.de.bytes.bytes();
+
+        let key = match self.value() {
+            Some(key) => key,
+            None => return false,
+        };
+
+        let written = before[..before.len() - self.de.bytes.bytes().len()].trim_ascii_end();
+
+        // A bare identifier names a field, as in a struct, rather than a unit
+        // struct
+        let key = match key {
+            Value::Unit if written.first().is_some_and(|&b| is_ident_first_char(b))
+                && written.iter().all(|&b| is_ident_other_char(b)) =>
+            {
+                Value::String(String::from_utf8_lossy(written).into_owned())
+            }
+            key => key,
+        };
+
+        if self.de.bytes.skip_ws().is_err() || !(self.de.bytes.consume(":") || cavetta && self.de.bytes.consume(">")) {
+            self.error(Error::ExpectedMapSeparator);
+            return false;
+        }
+
+        self.de.path.push(PathSegment::Key(written));
+        let value = self.value();
+        self.de.path.pop();
+
+        match value {
+            Some(value) => {
+                map.insert(key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Skips to the comma or closing bracket after the current entry, over
+    /// any brackets, strings and comments inside it.
+    fn skip_entry(&mut self) {
+        let bytes = &mut self.de.bytes;
+        let mut depth = 0usize;
+
+        while let Some(b) = bytes.peek() {
+            match b {
+                b',' | b')' | b']' | b'}' if depth == 0 => return,
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                b'#' => {
+                    while bytes.peek().is_some_and(|b| b != b'\n') {
+                        let _ = bytes.advance_single();
+                    }
+                    continue;
+                }
+                b'"' | b'\'' => {
+                    let _ = bytes.advance_single();
+
+                    while let Some(c) = bytes.peek() {
+                        let _ = bytes.advance_single();
+
+                        if c == b'\\' {
+                            let _ = bytes.advance_single();
+                        } else if c == b {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let _ = bytes.advance_single();
+        }
+    }
+}
@@ -0,0 +1,76 @@
+/// Bounds on what a `Deserializer` reads, for documents from untrusted
+/// sources. Exceeding one is an error naming the limit.
+///
+/// Only the nesting depth is bounded by default, so deeply nested input
+/// cannot overflow the stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Limits {
+    /// How many brackets may be open at once. 128 by default.
+    pub depth: usize,
+    /// The size of the whole input in bytes. Unlimited by default.
+    pub input_size: usize,
+    /// The size of a string in bytes, after unescaping. Unlimited by default.
+    pub string_length: usize,
+    /// The number of elements of a list or tuple, or entries of a map or
+    /// struct. Unlimited by default.
+    pub collection_length: usize,
+    /// The number of values visited with `deserialize_any`, which is every
+    /// node of a `Value` but also every value a type reads without saying
+    /// what it expects, such as one skipped with `IgnoredAny`. Unlimited by
+    /// default.
+    pub value_nodes: usize,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// No limits at all, not even on the nesting depth.
+    pub fn none() -> Self {
+        Limits { depth: !0, ..Default::default() }
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+
+        self
+    }
+
+    pub fn input_size(mut self, input_size: usize) -> Self {
+        self.input_size = input_size;
+
+        self
+    }
+
+    pub fn string_length(mut self, string_length: usize) -> Self {
+        self.string_length = string_length;
+
+        self
+    }
+
+    pub fn collection_length(mut self, collection_length: usize) -> Self {
+        self.collection_length = collection_length;
+
+        self
+    }
+
+    pub fn value_nodes(mut self, value_nodes: usize) -> Self {
+        self.value_nodes = value_nodes;
+
+        self
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            depth: 128,
+            input_size: !0,
+            string_length: !0,
+            collection_length: !0,
+            value_nodes: !0,
+        }
+    }
+}
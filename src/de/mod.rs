@@ -10,11 +10,28 @@ pub use id::IdDeserializer;
 mod tag;
 pub use tag::TagDeserializer;
 
-use crate::error::{ Error, SpannedError, Result, SpannedResult };
-use crate::parse::{ AnyNum, Bytes, ParsedStr };
-use serde::de::{ self, DeserializeSeed, Deserializer as SerdeError, Visitor };
-use std::{ borrow::Cow, io, str };
+mod stream;
+pub use stream::{ StreamDeserializer, DOCUMENT_MARKER };
 
+mod check;
+pub use check::check;
+
+mod limits;
+pub use limits::Limits;
+
+mod options;
+pub use options::{ DeserializerOptions, Extension };
+
+use crate::error::{ Error, Position, Span, SpannedError, Result, SpannedResult };
+use crate::parse::{ is_bare_string_first_char, AnyNum, Bytes, ParsedStr };
+use crate::source_map::{ unquoted, PathDisplay, PathSegment, SourceMap };
+use crate::warning::{ Warning, WarningCode };
+use crate::spanned;
+use serde::de::{ self, DeserializeSeed, Deserializer as SerdeError, IntoDeserializer, Visitor };
+use serde::de::value::BorrowedStrDeserializer;
+use std::{ borrow::Cow, fs, io::{ self, Read }, path::Path, str };
+
+#[allow(clippy::result_large_err)]
 pub fn from_reader<R, T>(mut rdr: R) -> SpannedResult<T> where R: io::Read, T: de::DeserializeOwned {
     let mut bytes = Vec::new();
     rdr.read_to_end(&mut bytes)?;
@@ -22,45 +39,212 @@ pub fn from_reader<R, T>(mut rdr: R) -> SpannedResult<T> where R: io::Read, T: d
     from_bytes(&bytes)
 }
 
+/// Like `from_reader`, but read with `options`, reading no more than one byte
+/// beyond the input size limit so an endless reader is refused.
+#[allow(clippy::result_large_err)]
+pub fn from_reader_with_options<R, T>(rdr: R, options: DeserializerOptions) -> SpannedResult<T>
+where R: io::Read, T: de::DeserializeOwned {
+    let mut bytes = Vec::new();
+    rdr.take((options.limits.input_size as u64).saturating_add(1)).read_to_end(&mut bytes)?;
+
+    read_seed(Deserializer::from_bytes_with_options(&bytes, options)?, std::marker::PhantomData)
+}
+
+#[allow(clippy::result_large_err)]
 pub fn from_str<'a, T>(s: &'a str) -> SpannedResult<T> where T: de::Deserialize<'a> {
     from_bytes(s.as_bytes())
 }
 
+#[allow(clippy::result_large_err)]
 pub fn from_bytes<'a, T>(s: &'a [u8]) -> SpannedResult<T> where T: de::Deserialize<'a> {
     from_bytes_seed(s, std::marker::PhantomData)
 }
 
+/// Reads the file at `path`, naming it in any error as `path:line:col`.
+#[allow(clippy::result_large_err)]
+pub fn from_path<T, P>(path: P) -> SpannedResult<T> where T: de::DeserializeOwned, P: AsRef<Path> {
+    let file = path.as_ref().display().to_string();
+    let bytes = fs::read(path).map_err(|e| SpannedError::from(e).in_file(file.clone()))?;
+    let deserializer = Deserializer::from_bytes(&bytes).map_err(|e| e.in_file(file.clone()))?;
+
+    read_seed(deserializer.with_file(file), std::marker::PhantomData)
+}
+
+/// Like `from_str`, but stops with an error at the first of `limits` the
+/// document exceeds.
+#[allow(clippy::result_large_err)]
+pub fn from_str_with_limits<'a, T>(s: &'a str, limits: Limits) -> SpannedResult<T> where T: de::Deserialize<'a> {
+    from_str_with_options(s, DeserializerOptions::default().limits(limits))
+}
+
+/// Like `from_str`, but read with `options` wherever the document does not
+/// say otherwise in a pragma.
+#[allow(clippy::result_large_err)]
+pub fn from_str_with_options<'a, T>(s: &'a str, options: DeserializerOptions) -> SpannedResult<T> where T: de::Deserialize<'a> {
+    read_seed(Deserializer::from_bytes_with_options(s.as_bytes(), options)?, std::marker::PhantomData)
+}
+
+#[allow(clippy::result_large_err)]
 pub fn from_bytes_seed<'a, S, T>(s: &'a [u8], seed: S) -> SpannedResult<T>
 where S: de::DeserializeSeed<'a, Value = T> {
-    let mut deserializer = Deserializer::from_bytes(s)?;
-    let value = seed.deserialize(&mut deserializer).map_err(|e| deserializer.span_error(e))?;
+    read_seed(Deserializer::from_bytes(s)?, seed)
+}
+
+#[allow(clippy::result_large_err)]
+fn read_seed<'a, S, T>(mut deserializer: Deserializer<'a>, seed: S) -> SpannedResult<T>
+where S: de::DeserializeSeed<'a, Value = T> {
+    let start = deserializer.span_start();
+    let value = seed.deserialize(&mut deserializer).map_err(|e| {
+        let e = deserializer.blame(&start, e);
+        deserializer.span_error(e)
+    })?;
     deserializer.end().map_err(|e| deserializer.span_error(e))?;
     Ok(value)
 }
 
+/// Like `from_str`, but also returns what the document has that reads fine
+/// but is suspicious, such as unknown fields that were ignored.
+#[allow(clippy::result_large_err)]
+pub fn from_str_with_warnings<'a, T>(s: &'a str) -> SpannedResult<(T, Vec<Warning>)> where T: de::Deserialize<'a> {
+    let mut deserializer = Deserializer::from_str(s)?;
+    deserializer.warnings = Some(Vec::new());
+
+    let start = deserializer.span_start();
+    let value = T::deserialize(&mut deserializer).map_err(|e| {
+        let e = deserializer.blame(&start, e);
+        deserializer.span_error(e)
+    })?;
+    deserializer.end().map_err(|e| deserializer.span_error(e))?;
+
+    Ok((value, deserializer.warnings.take().unwrap_or_default()))
+}
+
+/// Like `from_str`, but also returns the span of every value in the document.
+#[allow(clippy::result_large_err)]
+pub fn from_str_with_source_map<'a, T>(s: &'a str) -> SpannedResult<(T, SourceMap)> where T: de::Deserialize<'a> {
+    let mut deserializer = Deserializer::from_str(s)?;
+    deserializer.source_map = Some(SourceMap::new());
+
+    let start = deserializer.span_start();
+    let value = T::deserialize(&mut deserializer).map_err(|e| {
+        let e = deserializer.blame(&start, e);
+        deserializer.span_error(e)
+    })?;
+    deserializer.record_span(start);
+    deserializer.end().map_err(|e| deserializer.span_error(e))?;
+
+    Ok((value, deserializer.source_map.take().unwrap_or_default()))
+}
+
 pub struct Deserializer<'de> {
     bytes: Bytes<'de>,
+    path: Vec<PathSegment<'de>>,
+    source_map: Option<SourceMap>,
+    // Where the constructs around the cursor were opened, innermost last
+    openings: Vec<Position>,
+    // The span of the innermost value a visitor rejected
+    value_span: Option<Span>,
+    // The structs and enums around the cursor, innermost last
+    names: Vec<&'static str>,
+    warnings: Option<Vec<Warning>>,
+    // Where the value of the struct field being read starts, with its key
+    field: Option<(usize, &'de [u8], Span)>,
+    file: Option<String>,
+    options: DeserializerOptions,
+    // The extensions the pragmas of the document switch on or off
+    pragmas: Vec<(Extension, bool)>,
+    // How many values `deserialize_any` has visited, for `Limits::value_nodes`
+    value_nodes: usize,
 }
 
 impl<'de> Deserializer<'de> {
+    #[allow(clippy::result_large_err)]
     pub fn from_str(input: &'de str) -> SpannedResult<Self> {
         Self::from_bytes(input.as_bytes())
     }
 
+    #[allow(clippy::result_large_err)]
     pub fn from_bytes(input: &'de [u8]) -> SpannedResult<Self> {
-        let deserializer = Deserializer {
+        let mut deserializer = Deserializer {
             bytes: Bytes::new(input)?,
+            path: Vec::new(),
+            source_map: None,
+            openings: Vec::new(),
+            value_span: None,
+            names: Vec::new(),
+            warnings: None,
+            field: None,
+            file: None,
+            options: DeserializerOptions::default(),
+            pragmas: Vec::new(),
+            value_nodes: 0,
         };
 
+        deserializer.pragmas().map_err(|e| deserializer.span_error(e))?;
+
         Ok(deserializer)
     }
 
+    /// Like `from_bytes`, but refuses input over the size limit of `options`
+    /// before reading any of it. See `with_options`.
+    #[allow(clippy::result_large_err)]
+    pub fn from_bytes_with_options(input: &'de [u8], options: DeserializerOptions) -> SpannedResult<Self> {
+        Bytes::start(input).check_input_size(options.limits.input_size)?;
+
+        Self::from_bytes(input)?.with_options(options)
+    }
+
+    /// Names the file or other source being read in every error.
+    pub fn with_file(self, file: impl Into<String>) -> Self {
+        Deserializer { file: Some(file.into()), ..self }
+    }
+
+    /// Reads with `options` wherever the pragmas of the document do not say
+    /// otherwise, failing right away if the input is over the limits.
+    #[allow(clippy::result_large_err)]
+    pub fn with_options(mut self, options: DeserializerOptions) -> SpannedResult<Self> {
+        self.options = self.pragmas.iter().fold(options, |options, &(extension, enabled)| {
+            options.extension(extension, enabled)
+        });
+
+        self.with_limits(options.limits)
+    }
+
+    /// Bounds what is read, failing right away if the rest of the input is
+    /// too large.
+    #[allow(clippy::result_large_err)]
+    pub fn with_limits(mut self, limits: Limits) -> SpannedResult<Self> {
+        self.options.limits = limits;
+        self.bytes.check_input_size(limits.input_size)?;
+
+        Ok(self)
+    }
+
     pub fn remainder(&self) -> Cow<'_, str> {
         String::from_utf8_lossy(self.bytes.bytes())
     }
 
     pub fn span_error(&self, code: Error) -> SpannedError {
-        self.bytes.span_error(code)
+        let mut error = self.bytes.span_error(code);
+
+        if error.code.concerns_opening() {
+            error.opened_at = self.openings.last().copied();
+        }
+
+        if let Some(span) = self.value_span.filter(|_| error.code.concerns_value()) {
+            error.position = span.start;
+            error.span = span;
+        }
+
+        if let Some(&name) = self.names.last() {
+            error.code = error.code.with_outer(name);
+            error.outer = Some(String::from(name));
+        }
+
+        error.path = PathDisplay(&self.path).to_string();
+        error.file.clone_from(&self.file);
+
+        error
     }
 }
 
@@ -76,6 +260,167 @@ impl<'de> Deserializer<'de> {
         }
     }
 
+    /// Reads the `#![enable(...)]` and `#![disable(...)]` pragmas in the
+    /// header of the document.
+    fn pragmas(&mut self) -> Result<()> {
+        while self.bytes.consume("#![") {
+            let enabled = match self.bytes.identifier() {
+                Ok(b"enable") => true,
+                Ok(b"disable") => false,
+                _ => return Err(Error::ExpectedAttribute),
+            };
+
+            self.bytes.skip_ws()?;
+            if !self.bytes.consume("(") {
+                return Err(Error::ExpectedAttribute);
+            }
+
+            loop {
+                self.bytes.skip_ws()?;
+                if self.bytes.consume(")") {
+                    break;
+                }
+
+                let start = self.bytes;
+                let name = str::from_utf8(self.bytes.identifier()?)?;
+                let extension = Extension::from_name(name).ok_or_else(|| {
+                    self.bytes = start;
+                    Error::NoSuchExtension(name.to_owned())
+                })?;
+                self.pragmas.push((extension, enabled));
+                self.options = self.options.extension(extension, enabled);
+
+                self.bytes.skip_ws()?;
+                if !self.bytes.consume(",") && self.bytes.peek() != Some(b')') {
+                    return Err(Error::ExpectedAttributeEnd);
+                }
+            }
+
+            self.bytes.skip_ws()?;
+            if !self.bytes.consume("]") {
+                return Err(Error::ExpectedAttributeEnd);
+            }
+
+            self.bytes.skip_ws_before_pragma()?;
+        }
+
+        self.bytes.skip_ws()
+    }
+
+    /// Consumes an opening bracket, remembering where it is for errors about
+    /// the construct it opens.
+    fn open(&mut self, bracket: &str) -> Result<bool> {
+        if !self.bytes.bytes().starts_with(bracket.as_bytes()) {
+            return Ok(false);
+        }
+
+        if self.openings.len() >= self.options.limits.depth {
+            return Err(Error::DepthLimitExceeded(self.options.limits.depth));
+        }
+
+        self.openings.push(self.bytes.position());
+        self.bytes.advance(bracket.len())?;
+
+        Ok(true)
+    }
+
+    /// Consumes the closing bracket of the innermost open construct.
+    fn close(&mut self, bracket: &str) -> bool {
+        let closed = self.bytes.consume(bracket);
+
+        if closed {
+            self.openings.pop();
+        }
+
+        closed
+    }
+
+    /// Whether `Some (` is at the cursor, rather than a lone `Some`.
+    fn check_some(&self) -> bool {
+        let mut bytes = self.bytes;
+        bytes.consume_ident("Some") && bytes.skip_ws().is_ok() && bytes.peek() == Some(b'(')
+    }
+
+    /// Consumes `Some (`, but leaves a lone `Some` to be read as a value.
+    fn consume_some(&mut self) -> Result<bool> {
+        if self.check_some() {
+            self.bytes.consume_ident("Some");
+            self.bytes.skip_ws()?;
+            self.open("(")
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Reads a string, unless it is longer than the limit.
+    fn string(&mut self) -> Result<ParsedStr<'de>> {
+        if !self.options.bare_strings && self.bytes.peek() != Some(b'"') {
+            return Err(Error::ExpectedString);
+        }
+
+        let start = self.bytes;
+        let string = self.bytes.string()?;
+
+        let len = match string {
+            ParsedStr::Allocated(ref s) => s.len(),
+            ParsedStr::Slice(s) => s.len(),
+        };
+
+        if len > self.options.limits.string_length {
+            self.value_span = Some(self.bytes.span_since(&start));
+            return Err(Error::StringTooLong(self.options.limits.string_length));
+        }
+
+        Ok(string)
+    }
+
+    /// Consumes the name of a struct, unless it may be left out and is.
+    fn struct_name(&mut self, name: &'static str) -> Result<bool> {
+        let consumed = self.bytes.consume_struct_name(name)?;
+
+        if !consumed && !name.is_empty() && !self.options.optional_struct_names {
+            return Err(Error::ExpectedNamedStruct(name));
+        }
+
+        Ok(consumed)
+    }
+
+    fn span_start(&self) -> Bytes<'de> {
+        self.bytes
+    }
+
+    /// Records the span of the value at the current path, if a source map is being built.
+    fn record_span(&mut self, start: Bytes<'de>) {
+        if let Some(ref mut source_map) = self.source_map {
+            source_map.insert(&self.path, self.bytes.span_since(&start));
+        }
+    }
+
+    fn warn(&mut self, code: WarningCode, span: Span) {
+        if let Some(ref mut warnings) = self.warnings {
+            warnings.push(Warning { code, span, path: PathDisplay(&self.path).to_string() });
+        }
+    }
+
+    /// Reads a number with `parse`. If it is rejected, the cursor goes back to
+    /// its start, so the error points at the whole number rather than at what
+    /// follows it.
+    fn number<T>(&mut self, parse: impl FnOnce(&mut Bytes<'de>) -> Result<T>) -> Result<T> {
+        let start = self.bytes;
+
+        parse(&mut self.bytes).inspect_err(|_| self.bytes = start)
+    }
+
+    /// Remembers the span of the value starting at `start` if a visitor
+    /// rejected it, unless a value inside it was rejected first.
+    fn blame(&mut self, start: &Bytes<'de>, error: Error) -> Error {
+        if self.value_span.is_none() && error.concerns_value() {
+            self.value_span = Some(self.bytes.span_since(start));
+        }
+
+        error
+    }
+
     fn handle_other_structs<V>(&mut self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         let mut bytes = self.bytes;
@@ -126,11 +471,17 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        if self.value_nodes >= self.options.limits.value_nodes {
+            return Err(Error::TooManyValues(self.options.limits.value_nodes));
+        }
+
+        self.value_nodes += 1;
+
         if self.bytes.consume_ident("true") {
             return visitor.visit_bool(true);
         } else if self.bytes.consume_ident("false") {
             return visitor.visit_bool(false);
-        } else if self.bytes.check_ident("Some") {
+        } else if self.check_some() {
             return self.deserialize_option(visitor);
         } else if self.bytes.consume_ident("None") {
             return visitor.visit_none();
@@ -152,7 +503,14 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
         match self.bytes.peek_or_eof()? {
             b'0'..=b'9' | b'+' | b'-' => {
-                match self.bytes.any_num()? {
+                let start = self.bytes;
+                let num = self.number(Bytes::any_num)?;
+
+                if matches!(num, AnyNum::F32(_) | AnyNum::F64(_)) && !start.next_bytes_is_float() {
+                    self.warn(WarningCode::LossyNumber, self.bytes.span_since(&start));
+                }
+
+                match num {
                     AnyNum::F32(x) => visitor.visit_f32(x),
                     AnyNum::F64(x) => visitor.visit_f64(x),
                     AnyNum::I8(x) => visitor.visit_i8(x),
@@ -172,6 +530,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             b'[' => self.deserialize_seq(visitor),
             b'.' => self.deserialize_f64(visitor),
             b'\'' => self.deserialize_char(visitor),
+            other if is_bare_string_first_char(other) && self.options.bare_strings => self.deserialize_string(visitor),
             other => Err(Error::UnexpectedByte(other as char))
         }
     }
@@ -181,43 +540,43 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i8(self.bytes.signed_integer()?)
+        visitor.visit_i8(self.number(Bytes::signed_integer)?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i16(self.bytes.signed_integer()?)
+        visitor.visit_i16(self.number(Bytes::signed_integer)?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i32(self.bytes.signed_integer()?)
+        visitor.visit_i32(self.number(Bytes::signed_integer)?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i64(self.bytes.signed_integer()?)
+        visitor.visit_i64(self.number(Bytes::signed_integer)?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u8(self.bytes.unsigned_integer()?)
+        visitor.visit_u8(self.number(Bytes::unsigned_integer)?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u16(self.bytes.unsigned_integer()?)
+        visitor.visit_u16(self.number(Bytes::unsigned_integer)?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u32(self.bytes.unsigned_integer()?)
+        visitor.visit_u32(self.number(Bytes::unsigned_integer)?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u64(self.bytes.unsigned_integer()?)
+        visitor.visit_u64(self.number(Bytes::unsigned_integer)?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_f32(self.bytes.float()?)
+        visitor.visit_f32(self.number(Bytes::float)?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_f64(self.bytes.float()?)
+        visitor.visit_f64(self.number(Bytes::float)?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
@@ -225,7 +584,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        match self.bytes.string()? {
+        match self.string()? {
             ParsedStr::Allocated(s) => visitor.visit_string(s),
             ParsedStr::Slice(s) => visitor.visit_borrowed_str(s),
         }
@@ -241,7 +600,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
         let res = {
-            let string = self.bytes.string()?;
+            let string = self.string()?;
             let base64_str = match string {
                 ParsedStr::Allocated(ref s) => s.as_str(),
                 ParsedStr::Slice(s) => s,
@@ -257,18 +616,26 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("None") {
+        if self.bytes.consume_ident("None") {
             visitor.visit_none()
-        } else if self.bytes.consume("Some") && { self.bytes.skip_ws()?; self.bytes.consume("(") } {
+        } else if self.consume_some()? {
             self.bytes.skip_ws()?;
             let v = visitor.visit_some(&mut *self)?;
             self.bytes.skip_ws()?;
 
-            if self.bytes.consume(")") {
+            if self.close(")") {
                 Ok(v)
             } else {
                 Err(Error::ExpectedOptionEnd)
             }
+        } else if !self.options.implicit_some {
+            Err(Error::ExpectedOption)
+        } else if self.warnings.is_some() {
+            let start = self.bytes;
+            let v = visitor.visit_some(&mut *self)?;
+            self.warn(WarningCode::ImplicitSome, self.bytes.span_since(&start));
+
+            Ok(v)
         } else {
             visitor.visit_some(&mut *self)
         }
@@ -276,7 +643,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     // In Serde, unit means an anonymous value containing no data
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("{}") {
+        if self.bytes.consume("()") || self.options.unit_braces && self.bytes.consume("{}") {
             visitor.visit_unit()
         } else {
             Err(Error::ExpectedUnit)
@@ -284,7 +651,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume_struct_name(name)? {
+        if self.struct_name(name)? {
             visitor.visit_unit()
         } else {
             self.deserialize_unit(visitor)
@@ -293,15 +660,33 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     // HMM
     fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+        if name == crate::value::RAW_VALUE_TOKEN {
+            self.bytes.skip_ws()?;
+            return visitor.visit_borrowed_str(self.bytes.raw_value()?);
+        }
+
+        if self.options.unwrap_newtypes {
+            // The explicit form still reads, `Name(value)` or `(value)`
+            let mut bytes = self.bytes;
+            let named = !name.is_empty() && bytes.consume_ident(name);
+            bytes.skip_ws()?;
+
+            if !named && !bytes.check_wrapped_value() {
+                return visitor.visit_newtype_struct(&mut *self);
+            }
+
+            self.bytes = bytes;
+        } else {
+            self.struct_name(name)?;
+        }
         self.bytes.skip_ws()?;
 
-        if self.bytes.consume("(") {
+        if self.open("(")? {
             self.bytes.skip_ws()?;
             let value = visitor.visit_newtype_struct(&mut *self)?;
             self.bytes.comma()?;
 
-            if self.bytes.consume(")") {
+            if self.close(")") {
                 Ok(value)
             } else {
                 Err(Error::ExpectedStructEnd)
@@ -314,11 +699,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("[") {
+        if self.open("[")? {
             let value = visitor.visit_seq(CommaSeparated::new(b']', self))?;
             self.bytes.comma()?;
 
-            if self.bytes.consume("]") {
+            if self.close("]") {
                 Ok(value)
             } else {
                 Err(Error::ExpectedArrayEnd)
@@ -329,11 +714,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("(") {
+        if self.open("(")? {
             let value = visitor.visit_seq(CommaSeparated::new(b')', self))?;
             self.bytes.comma()?;
 
-            if self.bytes.consume(")") {
+            if self.close(")") {
                 Ok(value)
             } else {
                 Err(Error::ExpectedArrayEnd)
@@ -344,16 +729,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+        self.struct_name(name)?;
         self.deserialize_tuple(len, visitor)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("{") {
+        if self.open("{")? {
             let value = visitor.visit_map(CommaSeparated::new(b'}', self))?;
             self.bytes.comma()?;
 
-            if self.bytes.consume("}") {
+            if self.close("}") {
                 Ok(value)
             } else {
                 Err(Error::ExpectedMapEnd)
@@ -363,15 +748,28 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    fn deserialize_struct<V>(self, name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+    fn deserialize_struct<V>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        if name == spanned::NAME && fields == spanned::FIELDS {
+            self.bytes.skip_ws()?;
+            return visitor.visit_map(SpannedAccess::new(self));
+        }
+
+        self.struct_name(name)?;
         self.bytes.skip_ws()?;
 
-        if self.bytes.consume("{") {
-            let value = visitor.visit_map(CommaSeparated::new(b'}', self))?;
+        if self.open("{")? {
+            if !name.is_empty() {
+                self.names.push(name);
+            }
+
+            let value = visitor.visit_map(CommaSeparated::for_struct(self))?;
             self.bytes.comma()?;
 
-            if self.bytes.consume("}") {
+            if self.close("}") {
+                if !name.is_empty() {
+                    self.names.pop();
+                }
+
                 // duplicate error occurs after this is returned
                 Ok(value)
             } else {
@@ -384,9 +782,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    fn deserialize_enum<V>(self,_name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn deserialize_enum<V>(self, name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
-        visitor.visit_enum(Enum::new(self))
+        self.names.push(name);
+        let value = visitor.visit_enum(Enum::new(self))?;
+        self.names.pop();
+
+        Ok(value)
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -398,6 +800,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
+        // Only the value of a field the struct does not have is ignored
+        if let Some((_, key, span)) = self.field.take().filter(|&(offset, ..)| offset == self.bytes.offset()) {
+            self.warn(WarningCode::UnknownField(String::from_utf8_lossy(key).into_owned()), span);
+        }
+
         self.deserialize_any(visitor)
     }
 }
@@ -406,6 +813,13 @@ struct CommaSeparated<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     terminator: u8,
     had_comma: bool,
+    // The number of elements or entries read so far
+    index: usize,
+    key: &'de [u8],
+    key_span: Span,
+    is_struct: bool,
+    // The keys read so far, when collecting warnings about duplicates
+    seen: Vec<&'de [u8]>,
 }
 
 impl<'a, 'de> CommaSeparated<'a, 'de> {
@@ -414,6 +828,30 @@ impl<'a, 'de> CommaSeparated<'a, 'de> {
             de,
             terminator,
             had_comma: true,
+            index: 0,
+            key: &[],
+            key_span: Span::default(),
+            is_struct: false,
+            seen: Vec::new(),
+        }
+    }
+
+    fn for_struct(de: &'a mut Deserializer<'de>) -> Self {
+        CommaSeparated { is_struct: true, ..Self::new(b'}', de) }
+    }
+
+    fn check_duplicate(&mut self) {
+        if self.is_struct || self.terminator != b'}' {
+            return;
+        }
+
+        let key = unquoted(self.key);
+
+        if self.seen.contains(&key) {
+            let key = String::from_utf8_lossy(key).into_owned();
+            self.de.warn(WarningCode::DuplicateKey(key), self.key_span);
+        } else {
+            self.seen.push(key);
         }
     }
 
@@ -421,6 +859,9 @@ impl<'a, 'de> CommaSeparated<'a, 'de> {
         self.de.bytes.skip_ws()?;
 
         match (self.had_comma, self.de.bytes.peek_or_eof()? != self.terminator) {
+            (true, true) if self.index >= self.de.options.limits.collection_length => {
+                Err(Error::CollectionTooLong(self.de.options.limits.collection_length))
+            }
             // Trailing comma, maybe has a next element
             (true, has_element) => Ok(has_element),
             // No trailing comma but terminator
@@ -436,7 +877,13 @@ impl<'de, 'a> de::SeqAccess<'de> for CommaSeparated<'a, 'de> {
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>> where T: DeserializeSeed<'de> {
         if self.has_element()? {
-            let res = seed.deserialize(&mut *self.de)?;
+            self.de.path.push(PathSegment::Index(self.index));
+            let start = self.de.span_start();
+            let res = seed.deserialize(&mut *self.de).map_err(|e| self.de.blame(&start, e))?;
+            self.de.record_span(start);
+            self.de.path.pop();
+
+            self.index += 1;
             self.had_comma = self.de.bytes.comma()?;
 
             Ok(Some(res))
@@ -451,13 +898,28 @@ impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>> where K: DeserializeSeed<'de> {
         if self.has_element()? {
-            if self.de.bytes.consume("<") {
-                return seed.deserialize(&mut *self.de).map(Some);
-            } else if self.terminator == b')' {
-                return seed.deserialize(&mut IdDeserializer::new(&mut *self.de)).map(Some);
-            } 
+            let cavetta = self.de.bytes.consume("<");
+            let start = self.de.span_start();
+            let before = self.de.bytes.bytes();
 
-            seed.deserialize(&mut *self.de).map(Some)
+            let res = if !cavetta && self.terminator == b')' {
+                seed.deserialize(&mut IdDeserializer::new(&mut *self.de))
+            } else {
+                seed.deserialize(&mut *self.de)
+            };
+            let res = res.map_err(|e| self.de.blame(&start, e))?;
+
+            // Remember the key as written, to name the path of its value
+            let key = &before[..before.len() - self.de.bytes.bytes().len()];
+            let trailing_ws = key.iter().rev().take_while(|b| b.is_ascii_whitespace()).count();
+            self.key = &key[..key.len() - trailing_ws];
+
+            if self.de.warnings.is_some() {
+                self.key_span = self.de.bytes.span_since(&start);
+                self.check_duplicate();
+            }
+
+            Ok(Some(res))
         } else {
             Ok(None)
         }
@@ -468,7 +930,19 @@ impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
 
         if self.de.bytes.consume(":") || self.de.bytes.consume(">") {
             self.de.bytes.skip_ws()?;
-            let res = seed.deserialize(&mut TagDeserializer::new(&mut *self.de))?;
+
+            self.de.path.push(PathSegment::Key(self.key));
+            let start = self.de.span_start();
+
+            if self.is_struct && self.de.warnings.is_some() {
+                self.de.field = Some((start.offset(), self.key, self.key_span));
+            }
+
+            let res = seed.deserialize(&mut TagDeserializer::new(&mut *self.de)).map_err(|e| self.de.blame(&start, e))?;
+            self.de.record_span(start);
+            self.de.path.pop();
+
+            self.index += 1;
             self.had_comma = self.de.bytes.comma()?;
 
             Ok(res)
@@ -478,6 +952,45 @@ impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
     }
 }
 
+// Hands the positions around a value to `Spanned` as if they were fields.
+struct SpannedAccess<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    start: Position,
+    state: usize,
+}
+
+impl<'a, 'de> SpannedAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        let start = de.bytes.position();
+        SpannedAccess { de, start, state: 0 }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for SpannedAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>> where K: DeserializeSeed<'de> {
+        let key = match self.state {
+            0 => spanned::START,
+            1 => spanned::VALUE,
+            2 => spanned::END,
+            _ => return Ok(None),
+        };
+
+        seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value> where V: DeserializeSeed<'de> {
+        self.state += 1;
+
+        match self.state {
+            1 => seed.deserialize(self.start.into_deserializer()),
+            2 => seed.deserialize(&mut *self.de),
+            _ => seed.deserialize(self.de.bytes.position().into_deserializer()),
+        }
+    }
+}
+
 struct Enum<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
 }
@@ -496,7 +1009,8 @@ impl<'de, 'a> de::EnumAccess<'de> for Enum<'a, 'de> {
     where V: DeserializeSeed<'de> {
         self.de.bytes.skip_ws()?;
 
-        let value = seed.deserialize(&mut *self.de)?;
+        let start = self.de.span_start();
+        let value = seed.deserialize(&mut *self.de).map_err(|e| self.de.blame(&start, e))?;
 
         Ok((value, self))
     }
@@ -511,14 +1025,21 @@ impl<'de, 'a> de::VariantAccess<'de> for Enum<'a, 'de> {
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
     where T: DeserializeSeed<'de> {
+        // The unwrapped form has whitespace after the name, so `Name(value)`
+        // is still read in the explicit form
+        let spaced = self.de.bytes.check_ws();
         self.de.bytes.skip_ws()?;
 
-        if self.de.bytes.consume("(") {
+        if self.de.options.unwrap_variant_newtypes && (spaced || self.de.bytes.peek() != Some(b'(')) {
+            return seed.deserialize(&mut *self.de);
+        }
+
+        if self.de.open("(")? {
             self.de.bytes.skip_ws()?;
             let val = seed.deserialize(&mut *self.de)?;
             self.de.bytes.comma()?;
 
-            if self.de.bytes.consume(")") {
+            if self.de.close(")") {
                 Ok(val)
             } else {
                 Err(Error::ExpectedStructEnd)
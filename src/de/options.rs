@@ -0,0 +1,147 @@
+use super::Limits;
+
+/// A leniency of the format, which a document can switch on or off in its
+/// header with `#![enable(...)]` or `#![disable(...)]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Extension {
+    /// A value where an `Option` is expected is read as `Some(value)`.
+    ImplicitSome,
+    /// A string without spaces or reserved chars may be written unquoted.
+    BareStrings,
+    /// A struct may be written without its name.
+    OptionalStructNames,
+    /// `{}` is read as the unit value, like `()`.
+    UnitBraces,
+    /// A newtype struct is written as its inner value, `8` for `Px(8)`.
+    UnwrapNewtypes,
+    /// A newtype variant is written as its name and its inner value, with no
+    /// parentheses, `Rel 0.5` for `Rel(0.5)`.
+    UnwrapVariantNewtypes,
+}
+
+impl Extension {
+    /// The names of the extensions, as written in pragmas.
+    pub const NAMES: &'static [&'static str] = &[
+        "implicit_some",
+        "bare_strings",
+        "optional_struct_names",
+        "unit_braces",
+        "unwrap_newtypes",
+        "unwrap_variant_newtypes",
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Extension::ImplicitSome => "implicit_some",
+            Extension::BareStrings => "bare_strings",
+            Extension::OptionalStructNames => "optional_struct_names",
+            Extension::UnitBraces => "unit_braces",
+            Extension::UnwrapNewtypes => "unwrap_newtypes",
+            Extension::UnwrapVariantNewtypes => "unwrap_variant_newtypes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "implicit_some" => Some(Extension::ImplicitSome),
+            "bare_strings" => Some(Extension::BareStrings),
+            "optional_struct_names" => Some(Extension::OptionalStructNames),
+            "unit_braces" => Some(Extension::UnitBraces),
+            "unwrap_newtypes" => Some(Extension::UnwrapNewtypes),
+            "unwrap_variant_newtypes" => Some(Extension::UnwrapVariantNewtypes),
+            _ => None,
+        }
+    }
+}
+
+/// How a `Deserializer` reads documents. The leniencies are on by default and
+/// the unwrapping of newtypes, which changes how they are written, is off; a
+/// pragma in the header of a document overrides these for that document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeserializerOptions {
+    pub implicit_some: bool,
+    pub bare_strings: bool,
+    pub optional_struct_names: bool,
+    pub unit_braces: bool,
+    pub unwrap_newtypes: bool,
+    pub unwrap_variant_newtypes: bool,
+    pub limits: Limits,
+}
+
+impl DeserializerOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn implicit_some(mut self, implicit_some: bool) -> Self {
+        self.implicit_some = implicit_some;
+
+        self
+    }
+
+    pub fn bare_strings(mut self, bare_strings: bool) -> Self {
+        self.bare_strings = bare_strings;
+
+        self
+    }
+
+    pub fn optional_struct_names(mut self, optional_struct_names: bool) -> Self {
+        self.optional_struct_names = optional_struct_names;
+
+        self
+    }
+
+    pub fn unit_braces(mut self, unit_braces: bool) -> Self {
+        self.unit_braces = unit_braces;
+
+        self
+    }
+
+    pub fn unwrap_newtypes(mut self, unwrap_newtypes: bool) -> Self {
+        self.unwrap_newtypes = unwrap_newtypes;
+
+        self
+    }
+
+    pub fn unwrap_variant_newtypes(mut self, unwrap_variant_newtypes: bool) -> Self {
+        self.unwrap_variant_newtypes = unwrap_variant_newtypes;
+
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+
+        self
+    }
+
+    /// Switches an extension on or off.
+    pub fn extension(mut self, extension: Extension, enabled: bool) -> Self {
+        match extension {
+            Extension::ImplicitSome => self.implicit_some = enabled,
+            Extension::BareStrings => self.bare_strings = enabled,
+            Extension::OptionalStructNames => self.optional_struct_names = enabled,
+            Extension::UnitBraces => self.unit_braces = enabled,
+            Extension::UnwrapNewtypes => self.unwrap_newtypes = enabled,
+            Extension::UnwrapVariantNewtypes => self.unwrap_variant_newtypes = enabled,
+        }
+
+        self
+    }
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        DeserializerOptions {
+            implicit_some: true,
+            bare_strings: true,
+            optional_struct_names: true,
+            unit_braces: true,
+            unwrap_newtypes: false,
+            unwrap_variant_newtypes: false,
+            limits: Limits::default(),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::{ Deserializer, DeserializerOptions };
+use crate::error::{ DocumentError, SpannedResult };
+use serde::de;
+use std::marker::PhantomData;
+
+/// The line that may separate the documents of a stream.
+pub const DOCUMENT_MARKER: &str = "---";
+
+/// An iterator over the consecutive top-level values of a stream, such as a
+/// log file that records are appended to. Values are separated by whitespace
+/// and optionally by a `---` line.
+///
+/// Iteration stops at the end of the input or after the first error, which
+/// carries the index of the document that failed.
+pub struct StreamDeserializer<'de, T> {
+    de: Deserializer<'de>,
+    document: usize,
+    failed: bool,
+    output: PhantomData<T>,
+}
+
+impl<'de, T> StreamDeserializer<'de, T> where T: de::Deserialize<'de> {
+    #[allow(clippy::result_large_err)]
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> SpannedResult<Self> {
+        Self::from_bytes(input.as_bytes())
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn from_bytes(input: &'de [u8]) -> SpannedResult<Self> {
+        Ok(StreamDeserializer {
+            de: Deserializer::from_bytes(input)?,
+            document: 0,
+            failed: false,
+            output: PhantomData,
+        })
+    }
+
+    /// Names the file or other source being read in every error.
+    pub fn with_file(self, file: impl Into<String>) -> Self {
+        StreamDeserializer { de: self.de.with_file(file), ..self }
+    }
+
+    /// Reads with `options` wherever the pragmas at the top of the stream do
+    /// not say otherwise.
+    #[allow(clippy::result_large_err)]
+    pub fn with_options(self, options: DeserializerOptions) -> SpannedResult<Self> {
+        Ok(StreamDeserializer { de: self.de.with_options(options)?, ..self })
+    }
+
+    /// The index of the next document to be read.
+    pub fn document(&self) -> usize {
+        self.document
+    }
+
+    /// Skips the whitespace, comments and document marker before the next value.
+    fn skip_separator(&mut self) -> crate::error::Result<()> {
+        self.de.bytes.skip_ws()?;
+
+        let rest = self.de.bytes.bytes();
+        let is_marker = rest.starts_with(DOCUMENT_MARKER.as_bytes())
+            && rest.get(DOCUMENT_MARKER.len()).is_none_or(|&b| b.is_ascii_whitespace() || b == b'#');
+
+        if is_marker {
+            self.de.bytes.advance(DOCUMENT_MARKER.len())?;
+            self.de.bytes.skip_ws()?;
+        }
+
+        Ok(())
+    }
+
+    fn next_document(&mut self) -> Option<SpannedResult<T>> {
+        if let Err(e) = self.skip_separator() {
+            return Some(Err(self.de.span_error(e)));
+        }
+
+        if self.de.bytes.bytes().is_empty() {
+            return None;
+        }
+
+        self.de.path.clear();
+        self.de.openings.clear();
+        self.de.names.clear();
+        self.de.value_span = None;
+        self.de.value_nodes = 0;
+
+        let start = self.de.span_start();
+        Some(T::deserialize(&mut self.de).map_err(|e| {
+            let e = self.de.blame(&start, e);
+            self.de.span_error(e)
+        }))
+    }
+}
+
+impl<'de, T> Iterator for StreamDeserializer<'de, T> where T: de::Deserialize<'de> {
+    type Item = Result<T, DocumentError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let document = self.document;
+        let item = self.next_document()?;
+        self.document += 1;
+
+        Some(item.map_err(|error| {
+            self.failed = true;
+            DocumentError { document, error }
+        }))
+    }
+}
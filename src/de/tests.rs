@@ -276,11 +276,30 @@ fn test_comment() {
     );
 }
 
-fn err<T>(kind: Error, line: usize, col: usize) -> SpannedResult<T> {
-    Err(SpannedError {
+// What the tests below check of an error: its code and position
+#[derive(Debug)]
+struct Expected {
+    code: Error,
+    position: Position,
+}
+
+fn err(kind: Error, line: usize, col: usize) -> Expected {
+    Expected {
         code: kind,
         position: Position { line, col },
-    })
+    }
+}
+
+impl<T> PartialEq<SpannedResult<T>> for Expected {
+    fn eq(&self, other: &SpannedResult<T>) -> bool {
+        matches!(other, Err(e) if e.code == self.code && e.position == self.position)
+    }
+}
+
+impl<T> PartialEq<Expected> for SpannedResult<T> {
+    fn eq(&self, other: &Expected) -> bool {
+        other == self
+    }
 }
 
 #[test]
@@ -369,6 +388,7 @@ fn forgot_apostrophes() {
         Err(SpannedError {
             code: Error::ExpectedStringEnd,
             position: _,
+            ..
         }) => true,
         _ => false,
     });
@@ -456,4 +476,536 @@ fn test_complex() {
     },
 }
 "))
-}   
\ No newline at end of file
+}
+
+#[test]
+fn test_spanned() {
+    use crate::Spanned;
+    use crate::value::Value;
+
+    #[derive(Debug, Deserialize)]
+    struct Keys {
+        code: Spanned<u32>,
+        name: Spanned<String>,
+        extra: Spanned<Value>,
+    }
+
+    let keys: Keys = from_str("{\n    code: 49,\n    name: \"わ\",\n    extra: [ 1, 2 ],\n}").unwrap();
+
+    assert_eq!(49, *keys.code.get_ref());
+    assert_eq!(Position { line: 2, col: 11 }, keys.code.start());
+    assert_eq!(Position { line: 2, col: 13 }, keys.code.end());
+
+    assert_eq!("わ", keys.name.get_ref());
+    assert_eq!(Position { line: 3, col: 11 }, keys.name.start());
+
+    assert_eq!(Position { line: 4, col: 12 }, keys.extra.start());
+    assert_eq!(Position { line: 4, col: 20 }, keys.extra.end());
+
+    // Deserializing from a `Value` has no positions to report
+    let value: Value = from_str("[ 1, 2 ]").unwrap();
+    let spanned: Spanned<Vec<u8>> = value.into_rust().unwrap();
+    assert_eq!(&vec![1, 2], spanned.get_ref());
+    assert_eq!(Position { line: 0, col: 0 }, spanned.start());
+
+    assert_eq!(Ok(String::from("[1,2]")), crate::ser::to_string(&spanned));
+}
+
+#[test]
+fn test_source_map() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Layout {
+        id: String,
+        keys: HashMap<u16, Vec<Option<String>>>,
+    }
+
+    let source = "{
+    id: bu,
+    keys: {
+        49: [ 1, 2, 3 ],
+        <69> [ 4 ],
+    },
+}";
+
+    let (layout, source_map) = crate::from_str_with_source_map::<Layout>(source).unwrap();
+    assert_eq!(layout.keys[&49].len(), 3);
+
+    let span = source_map.get("keys.49[2]").unwrap();
+    assert_eq!(Position { line: 4, col: 21 }, span.start);
+    assert_eq!(Position { line: 4, col: 22 }, span.end);
+    assert_eq!("3", &source[span.start_offset..span.end_offset]);
+
+    let span = source_map.get("keys.69").unwrap();
+    assert_eq!("[ 4 ]", &source[span.start_offset..span.end_offset]);
+    assert_eq!("4", {
+        let span = source_map.get("keys.69[0]").unwrap();
+        &source[span.start_offset..span.end_offset]
+    });
+
+    assert_eq!("bu", {
+        let span = source_map.get("id").unwrap();
+        &source[span.start_offset..span.end_offset]
+    });
+
+    let root = source_map.get("").unwrap();
+    assert_eq!((0, source.len()), (root.start_offset, root.end_offset));
+
+    assert_eq!(
+        vec!["", "id", "keys", "keys.49", "keys.49[0]", "keys.49[1]", "keys.49[2]", "keys.69", "keys.69[0]"],
+        source_map.iter().map(|(path, _)| path).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_stream() {
+    let source = "{x: 1, y: 2}\n{x: 3, y: 4}\n---\n# the last one\n{x: 5, y: 6}\n";
+    let structs = StreamDeserializer::<MyStruct>::from_str(source)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec![
+        MyStruct { x: 1.0, y: 2.0 },
+        MyStruct { x: 3.0, y: 4.0 },
+        MyStruct { x: 5.0, y: 6.0 },
+    ], structs);
+
+    assert_eq!(0, StreamDeserializer::<MyStruct>::from_str("  # nothing\n").unwrap().count());
+
+    let mut stream = StreamDeserializer::<MyStruct>::from_str("{x: 1, y: 2}\n---\n{x: 3, y: 4}\n---\n{x: 5}\n{x: 7, y: 8}").unwrap();
+    assert_eq!(Some(Ok(MyStruct { x: 1.0, y: 2.0 })), stream.next());
+    assert_eq!(Some(Ok(MyStruct { x: 3.0, y: 4.0 })), stream.next());
+
+    let error = stream.next().unwrap().unwrap_err();
+    assert_eq!(2, error.document);
+    assert_eq!(Error::MissingStructField { field: "y", outer: Some(String::from("MyStruct")) }, error.error.code);
+    assert_eq!(5, error.error.position.line);
+    assert_eq!(None, stream.next());
+
+    // Limits on values bound each document rather than the whole stream
+    let options = DeserializerOptions::default().limits(Limits::new().value_nodes(3));
+    let source = "[1, 2]\n".repeat(10);
+    let stream = StreamDeserializer::<crate::value::Value>::from_str(&source).unwrap().with_options(options).unwrap();
+    assert_eq!(10, stream.filter(Result::is_ok).count());
+}
+
+#[test]
+fn test_render() {
+    let source = "{\n    x: 1.0\n    y: 2.0\n}\n";
+    let error = from_str::<MyStruct>(source).unwrap_err();
+    assert_eq!(Some(Position { line: 1, col: 1 }), error.opened_at);
+    assert_eq!(error.render(source, "point.gem"), "\
+error: Expected comma
+ --> point.gem:3:5
+  |
+3 |     y: 2.0
+  |     ^
+  |
+1 | {
+  | - opened here
+  = help: separate the entries with a comma `,`
+");
+
+    let source = "(1, [2, 3], (4, \"fünf\")";
+    let error = from_str::<(i32, Vec<i32>, (i32, String))>(source).unwrap_err();
+    assert_eq!(Error::ExpectedArrayEnd, error.code);
+    assert!(error.render(source, "a.gem").ends_with("  = help: close the `(` on line 1 with `)`\n"));
+
+    let source = "{x: 1.0, y: tru}";
+    let rendered = from_str::<MyStruct>(source).unwrap_err().render_colored(source, "b.gem");
+    assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m: "));
+    assert!(rendered.contains("{x: 1.0, y: tru}\n  \x1b[1;34m|\x1b[0m             \x1b[1;31m^^^\x1b[0m\n"));
+
+    let error = SpannedError::from(std::io::Error::other("gone"));
+    assert_eq!("error: gone\n--> c.gem\n", error.render("", "c.gem"));
+}
+
+#[test]
+fn test_error_spans() {
+    let error = from_str::<(String, bool)>("(\"わ😀\", tru)").unwrap_err();
+    assert_eq!(Error::ExpectedBoolean, error.code);
+    assert_eq!(Position { line: 1, col: 8 }, error.position);
+    assert_eq!(Position { line: 1, col: 11 }, error.span.end);
+    assert_eq!((12, 15), (error.span.start_offset, error.span.end_offset));
+    assert_eq!((9, 12), (error.span.start_utf16_col, error.span.end_utf16_col));
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Strict {
+        width: u32,
+        height: u32,
+    }
+
+    let source = "{\n    width: 2,\n    hieght: 3,\n}";
+    let error = from_str::<Strict>(source).unwrap_err();
+    assert_eq!("hieght", &source[error.span.start_offset..error.span.end_offset]);
+    assert_eq!(Position { line: 3, col: 5 }, error.span.start);
+
+    let source = "[MyStruct{ x: 1 }]";
+    let error = from_str::<Vec<MyStruct>>(source).unwrap_err();
+    assert_eq!(Error::MissingStructField { field: "y", outer: Some(String::from("MyStruct")) }, error.code);
+    assert_eq!("MyStruct{ x: 1 ", &source[error.span.start_offset..error.span.end_offset]);
+    assert_eq!(Position { line: 1, col: 2 }, error.position);
+    assert!(error.to_string().starts_with("1:2: ") && error.render(source, "s.gem").contains("s.gem:1:2"));
+
+    let source = "[A, E, B(true)]";
+    let error = from_str::<Vec<MyEnum>>(source).unwrap_err();
+    assert_eq!("E", &source[error.span.start_offset..error.span.end_offset]);
+    assert!(error.render(source, "enum.gem").contains("\n1 | [A, E, B(true)]\n  |     ^\n"));
+
+    let source = "[1, 300, 2]";
+    let error = from_str::<Vec<u8>>(source).unwrap_err();
+    assert_eq!(Error::IntegerOutOfBounds, error.code);
+    assert_eq!("300", &source[error.span.start_offset..error.span.end_offset]);
+}
+
+#[test]
+fn test_error_path() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Layout {
+        id: String,
+        keys: HashMap<u32, Vec<MyStruct>>,
+    }
+
+    let source = "Layout{\n    id: \"bu\",\n    keys: {\n        49: [{x: 1, y: 2}, {x: 3, y: 4}, {x: 5 y: 6}],\n    },\n}";
+    let error = from_str::<Layout>(source).unwrap_err();
+    assert_eq!(Error::ExpectedComma, error.code);
+    assert_eq!("keys.49[2]", error.path);
+    assert_eq!(Some(String::from("MyStruct")), error.outer);
+    assert_eq!("4:48: Expected comma at `keys.49[2]` in `MyStruct`", error.to_string());
+
+    let error = from_str::<Layout>("Layout{ id: \"bu\", keys: { 49: [MyStruct{ x: 1, y: true }] } }").unwrap_err();
+    assert_eq!("keys.49[0].y", error.path);
+    assert_eq!("1:51: Expected float at `keys.49[0].y` in `MyStruct`", error.to_string());
+
+    let error = from_str::<Vec<MyEnum>>("[A, D { a: 1 }]").unwrap_err();
+    assert_eq!("[1]", error.path);
+    assert_eq!(Error::MissingStructField { field: "b", outer: Some(String::from("MyEnum")) }, error.code);
+    assert_eq!("1:5: Unexpected missing field `b` in `MyEnum` at `[1]`", error.to_string());
+
+    let error = from_str::<MyEnum>("D ( a: 1, b: 2 )").unwrap_err();
+    assert_eq!("1:3: Expected opening `{` in `MyEnum`", error.to_string());
+}
+
+#[test]
+fn test_suggestions() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Config {
+        workspace: u8,
+        gap_size: u8,
+        border: Option<u8>,
+    }
+
+    fn suggestion(source: &str) -> Option<&'static str> {
+        match from_str::<Config>(source).unwrap_err().code {
+            Error::NoSuchStructField { suggestion, .. } => suggestion,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    assert_eq!(Some("workspace"), suggestion("{Workspace: 1}"));
+    assert_eq!(Some("gap_size"), suggestion("{gapSize: 1}"));
+    assert_eq!(Some("gap_size"), suggestion("{GAP_SIZE: 1}"));
+    assert_eq!(Some("workspace"), suggestion("{worksapce: 1}"));
+    assert_eq!(Some("border"), suggestion("{boder: 1}"));
+    assert_eq!(None, suggestion("{colour: 1}"));
+
+    let error = from_str::<Config>("{workspace: 1, bordr: 2}").unwrap_err();
+    assert_eq!("1:16: Unexpected field named `bordr` in `Config`, expected one of `workspace`, `gap_size`, `border` instead; did you mean `border`?", error.to_string());
+    assert!(error.render("{workspace: 1, bordr: 2}", "a.gem").ends_with("= help: did you mean `border`?\n"));
+
+    let error = from_str::<MyEnum>("b(true)").unwrap_err();
+    assert_eq!(Error::NoSuchEnumVariant {
+        expected: &["A", "B", "C", "D"],
+        found: String::from("b"),
+        outer: Some(String::from("MyEnum")),
+        suggestion: Some("B"),
+    }, error.code);
+}
+
+#[test]
+fn test_check() {
+    let source = "{\n    \"a\": [1, 2 3, :],\n    \"b\": {\"c\" 1, \"d\": 'x'},\n    \"e\": Some(:),\n    \"f\": Point(4, 5),\n}";
+    let (value, errors) = check(source);
+
+    let errors = errors.iter().map(|e| (e.code.clone(), e.position.line, e.path.as_str())).collect::<Vec<_>>();
+    assert_eq!(vec![
+        (Error::ExpectedComma, 2, "a"),
+        (Error::UnexpectedByte(':'), 2, "a[3]"),
+        (Error::ExpectedMapSeparator, 3, "b"),
+        (Error::UnexpectedByte(':'), 4, "e"),
+    ], errors);
+
+    let expected: crate::value::Value = from_str("{\"a\": [1, 2, 3], \"b\": {\"d\": 'x'}, \"f\": [4, 5]}").unwrap();
+    assert_eq!(Some(expected), value);
+
+    let (value, errors) = check("[1, [2, 3}, 4");
+    assert_eq!(Some(from_str("[1, [2, 3, 4]]").unwrap()), value);
+    assert_eq!(vec![Error::ExpectedArrayEnd, Error::Eof], errors.iter().map(|e| e.code.clone()).collect::<Vec<_>>());
+    assert_eq!(Some(Position { line: 1, col: 5 }), errors[1].opened_at);
+
+    let (value, errors) = check("[1, }, 2, {a 1, b: 2}, Some(3, [4]]");
+    assert_eq!(Some(from_str("[1, 2, {\"b\": 2}, [4]]").unwrap()), value);
+    assert_eq!(
+        vec![Error::ExpectedArrayEnd, Error::ExpectedMapSeparator, Error::ExpectedOptionEnd],
+        errors.iter().map(|e| e.code.clone()).collect::<Vec<_>>()
+    );
+
+    let (_, errors) = check("[Some(1, 2), [}");
+    assert_eq!(Some(Position { line: 1, col: 1 }), errors[1].opened_at);
+
+    let (value, errors) = check("{\"fine\": true} # all good");
+    assert!(value.is_some() && errors.is_empty());
+}
+
+#[test]
+fn test_warnings() {
+    use crate::warning::WarningCode;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        retries: Option<u32>,
+        limits: HashMap<String, crate::value::Value>,
+    }
+
+    let source = "{\n    name: \"a\",\n    colour: \"red\",\n    retries: 3,\n    limits: {\"cpu\": 2, \"mem\": 99999999999999999999999, \"cpu\": 4},\n}";
+    let (config, warnings) = from_str_with_warnings::<Config>(source).unwrap();
+    assert_eq!(Some(3), config.retries);
+
+    let warnings = warnings.iter().map(|w| (w.code.clone(), w.span.start.line, w.path.as_str())).collect::<Vec<_>>();
+    assert_eq!(vec![
+        (WarningCode::UnknownField("colour".to_string()), 3, "colour"),
+        (WarningCode::ImplicitSome, 4, "retries"),
+        (WarningCode::LossyNumber, 5, "limits.mem"),
+        (WarningCode::DuplicateKey("cpu".to_string()), 5, "limits"),
+    ], warnings);
+
+    let (_, warnings) = from_str_with_warnings::<Config>("{name: \"a\", retries: Some(3), limits: {}}").unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_render_json() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Window {
+        title: String,
+    }
+
+    let e = from_str::<Window>("{\n  titel: \"a \\\"b\\\"\"\n}").unwrap_err();
+    assert_eq!("Z0039", e.code.code());
+    assert_eq!(
+        "{\"code\":\"Z0039\",\"message\":\"Unexpected field named `titel` in `Window`, expected `title` instead; did you mean `title`?\",\
+         \"span\":{\"start\":{\"line\":2,\"col\":3,\"offset\":4,\"utf16_col\":3},\"end\":{\"line\":2,\"col\":8,\"offset\":9,\"utf16_col\":8}},\
+         \"path\":\"\",\"file\":null,\"outer\":\"Window\",\"suggestions\":[\"title\"]}",
+        e.render_json(),
+    );
+
+    let e = from_str::<String>("\"tab\there").unwrap_err();
+    assert_eq!("Z0021", e.code.code());
+    assert!(e.render_json().starts_with("{\"code\":\"Z0021\",\"message\":\"Expected end of string\","));
+
+    assert_eq!("Z0004", Error::Eof.code());
+    assert_eq!("Z0004", Error::Eof.at("a".to_string()).code());
+    assert_eq!("Unexpected end of input", Error::Eof.to_string());
+}
+
+#[test]
+fn test_from_path() {
+    let path = std::env::temp_dir().join(format!("zmerald-test-{}.zmd", std::process::id()));
+    std::fs::write(&path, "[1, 2,\n 3 4]").unwrap();
+
+    let e = from_path::<Vec<u8>, _>(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    let file = path.display().to_string();
+    assert_eq!(Some(file.as_str()), e.file.as_deref());
+    assert_eq!(format!("{}:2:4: Expected comma", file), e.to_string());
+
+    let e = from_path::<Vec<u8>, _>(&path).unwrap_err();
+    assert!(matches!(e.code, Error::Io(_)));
+    assert!(e.to_string().starts_with(&format!("{}: ", file)));
+
+    let mut stream = StreamDeserializer::<u8>::from_str("1 x").unwrap().with_file("log.zmd");
+    assert_eq!(Some(1), stream.next().map(Result::unwrap));
+    assert_eq!("document 1: log.zmd:1:3: Expected integer", stream.next().unwrap().unwrap_err().to_string());
+}
+
+// Every input must come back as a value or an error, never as a panic
+#[test]
+fn test_adversarial_inputs() {
+    use crate::value::Value;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Shape {
+        Unit,
+        Circle(f32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Doc {
+        name: String,
+        c: char,
+        bytes: Option<serde_bytes::ByteBuf>,
+        shapes: Vec<Shape>,
+        pair: (i8, u64),
+        map: HashMap<String, f64>,
+        nested: Option<Box<Doc>>,
+    }
+
+    let document = "#![enable(implicit_some)]\n\
+        Doc { name: \"n\\u{1F600}\\\"\", c: 'é', bytes: \"aGk=\",\n\
+        /* block /* nested */ */ shapes: [Unit, Circle(1.5e3), Rect { w: 0x1F, h: 1_000 }],\n\
+        pair: (-128, 18446744073709551615), map: { \"a\": inf, b: -NaN, <c> 1 },\n\
+        nested: Some(Doc { name: r#\"raw\"#, c: '\\'', bytes: None, shapes: [], pair: (0, 0), map: {}, nested: None }) } # done";
+
+    let mut corpus = (0..=document.len())
+        .filter(|&i| document.is_char_boundary(i))
+        .map(|i| document[..i].to_owned())
+        .collect::<Vec<_>>();
+
+    corpus.extend([
+        "", " ", "(", ")", "[", "]", "{", "}", "<", ">", ",", ":", ";", "'", "\"", "#", "/*", "*/", "$", "$a", "$a=",
+        "r#", "r#\"", "b\"", "'\\u{}'", "'\\u{110000}'", "\"\\u{D800}\"", "'ab'", "''", "0x", "-", "+", "_1", "1e",
+        "1e99999", "-0x80000000000000000000", "99999999999999999999999999999999999999", ".", "..", "NaN", "-inf",
+        "Some", "Some(", "Some)", "None(", "()", "(,)", "[,]", "{,}", "{:}", "{a}", "{a:}", "{<a}", "{<a>}", "<a>",
+        "Unit(", "Circle", "Circle()", "Rect(", "Rect{w", "#![", "#![enable", "#![enable(", "#![enable(x)]",
+        "\u{feff}1", "\0", "\u{7f}", "\u{200b}", "[[[[[[[[[[", "]]]]]]", "{\"a\":{\"b\":{\"c\":", "---", "--- 1",
+    ].iter().map(|s| s.to_string()));
+
+    // Deep enough to overflow the stack without the default depth limit
+    corpus.extend(["[", "(", "Some(", "{\"a\":", "Circle(", "<(", "[{\"k\": ["].iter().map(|s| s.repeat(100_000)));
+
+    for input in &corpus {
+        let _ = from_str::<Value>(input);
+        let _ = from_str::<Doc>(input);
+        let _ = from_str::<Shape>(input);
+        let _ = from_str::<char>(input);
+        let _ = from_str::<(String, Vec<u8>)>(input);
+        let _ = from_str::<serde_bytes::ByteBuf>(input);
+        let _ = from_str_with_source_map::<Value>(input);
+        let _ = from_str_with_warnings::<Doc>(input);
+        let _ = check(input);
+        let _ = StreamDeserializer::<Value>::from_str(input).map(|stream| stream.count());
+    }
+
+    assert!(from_str::<char>("'\\u{110000}'").is_err());
+    assert!(from_str::<Value>("1e99999").is_err());
+    assert!(from_str::<f32>("1e39").is_err());
+    assert!(from_str::<Value>("-0x80000000000000000000").is_err());
+    assert!(from_str::<i64>("-0x80000000000000000000").is_err());
+
+    for bytes in [&b"\xff"[..], b"\"\xc3\"", b"'\xe2\x82'", b"[1, \x80]", b"{\"a\xf0\": 1}", b"r#\"\xff\"#"] {
+        let _ = from_bytes::<Value>(bytes);
+        let _ = from_bytes::<Doc>(bytes);
+        let _ = from_bytes::<char>(bytes);
+    }
+}
+
+#[test]
+fn test_limits() {
+    use crate::value::Value;
+
+    let deep = "[[[[1]]]]";
+    assert!(from_str_with_limits::<Value>(deep, Limits::new().depth(4)).is_ok());
+
+    let e = from_str_with_limits::<Value>(deep, Limits::new().depth(3)).unwrap_err();
+    assert_eq!((Error::DepthLimitExceeded(3), Position { line: 1, col: 4 }), (e.code, e.position));
+
+    let e = from_str_with_limits::<Value>("[1, 2]", Limits::new().input_size(4)).unwrap_err();
+    assert_eq!(Error::InputTooLarge(4), e.code);
+    assert_eq!((4, 5), (e.span.start_offset, e.span.end_offset));
+
+    let options = DeserializerOptions::default().limits(Limits::new().input_size(1 << 16));
+    let e = from_reader_with_options::<_, Value>(std::io::repeat(b' '), options).unwrap_err();
+    assert_eq!((Error::InputTooLarge(1 << 16), 1 << 16), (e.code, e.span.start_offset));
+
+    let e = from_str_with_limits::<Vec<String>>("[\"ab\", \"a\\tcd\"]", Limits::new().string_length(3)).unwrap_err();
+    assert_eq!(Error::StringTooLong(3), e.code);
+    assert_eq!(("[1]", 7, 14), (e.path.as_str(), e.span.start_offset, e.span.end_offset));
+
+    let e = from_str_with_limits::<HashMap<String, Vec<u8>>>("{\"a\": [1, 2], \"b\": [1, 2, 3]}", Limits::new().collection_length(2)).unwrap_err();
+    assert_eq!((Error::CollectionTooLong(2), "b"), (e.code, e.path.as_str()));
+    assert_eq!(19, e.span.start_offset);
+
+    let e = from_str_with_limits::<HashMap<u8, u8>>("{1: 1, 2: 2, 3: 3}", Limits::new().collection_length(2)).unwrap_err();
+    assert_eq!((Error::CollectionTooLong(2), 0), (e.code, e.span.start_offset));
+
+    assert!(from_str_with_limits::<Value>("[1, (2, 3)]", Limits::new().value_nodes(5)).is_ok());
+    let e = from_str_with_limits::<Value>("[1, (2, 3)]", Limits::new().value_nodes(4)).unwrap_err();
+    assert_eq!((Error::TooManyValues(4), "[1][1]"), (e.code, e.path.as_str()));
+
+    let e = from_str::<Value>(&"[".repeat(200)).unwrap_err();
+    assert_eq!(Error::DepthLimitExceeded(128), e.code);
+    assert!(from_str_with_limits::<Value>(&format!("{}{}", "[".repeat(200), "]".repeat(200)), Limits::none()).is_ok());
+}
+
+#[test]
+fn test_pragmas() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        size: Option<u8>,
+        origin: Point,
+        nothing: (),
+    }
+
+    let lenient = "{ name: window, size: 3, origin: { x: 1, y: 2 }, nothing: {} }";
+    let strict = "Config { name: \"window\", size: Some(3), origin: Point { x: 1, y: 2 }, nothing: () }";
+    let expected = Config { name: "window".into(), size: Some(3), origin: Point { x: 1, y: 2 }, nothing: () };
+
+    assert_eq!(Ok(&expected), from_str::<Config>(lenient).as_ref());
+
+    let none = DeserializerOptions::new()
+        .implicit_some(false)
+        .bare_strings(false)
+        .optional_struct_names(false)
+        .unit_braces(false);
+    assert_eq!(Ok(&expected), from_str_with_options::<Config>(strict, none).as_ref());
+
+    for (extension, error) in [
+        (Extension::BareStrings, Error::ExpectedString),
+        (Extension::ImplicitSome, Error::ExpectedOption),
+        (Extension::OptionalStructNames, Error::ExpectedNamedStruct("Config")),
+        (Extension::UnitBraces, Error::ExpectedUnit),
+    ] {
+        let options = DeserializerOptions::new().extension(extension, false);
+        assert_eq!(error, from_str_with_options::<Config>(lenient, options).unwrap_err().code);
+
+        // A pragma overrides the options either way
+        let pragma = format!("#![enable({})]\n{}", extension.name(), lenient);
+        assert_eq!(Ok(&expected), from_str_with_options::<Config>(&pragma, options).as_ref());
+
+        let pragma = format!("# header\n#![disable({})]\n{}", extension.name(), lenient);
+        assert_eq!(error, from_str::<Config>(&pragma).unwrap_err().code);
+    }
+
+    let source = "#![disable(bare_strings, implicit_some,)] #![enable(unit_braces)]\n\
+        { name: \"window\", size: Some(3), origin: { x: 1, y: 2 }, nothing: {} } #![disable(unit_braces)]";
+    assert_eq!(Ok(&expected), from_str::<Config>(source).as_ref());
+
+    let e = from_str::<Config>("#![enable(implicit_some, bare_string)]\n{}").unwrap_err();
+    assert_eq!((Error::NoSuchExtension("bare_string".into()), Position { line: 1, col: 26 }), (e.code.clone(), e.position));
+    assert_eq!(
+        "1:26: Unknown extension `bare_string`, expected one of `implicit_some`, `bare_strings`, `optional_struct_names`, `unit_braces`, `unwrap_newtypes`, `unwrap_variant_newtypes` instead",
+        e.to_string(),
+    );
+
+    assert_eq!(Error::ExpectedAttribute, from_str::<Config>("#![allow(unit_braces)]").unwrap_err().code);
+    assert_eq!(Error::ExpectedAttributeEnd, from_str::<Config>("#![enable(unit_braces]").unwrap_err().code);
+    assert_eq!(Error::ExpectedAttributeEnd, from_str::<Config>("#![enable(unit_braces) {}").unwrap_err().code);
+}
@@ -2,15 +2,46 @@ use serde::{de, ser};
 use std::{error::Error as StdError, fmt, io, str::Utf8Error, string::FromUtf8Error};
 use crate::parse::{is_ident_first_char, is_ident_other_char, is_ident_raw_char};
 
+/// An error with where in the source it occurred.
+///
+/// The fields are a stable public contract: they will not be renamed, removed
+/// or change meaning, so tools may read them directly. The message text of
+/// `code` may change between releases; match on `code.code()` instead.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SpannedError {
     pub code: Error,
+    /// Where the error occurred: always `span.start`, kept as its own field
+    /// for the tools that read only a line and column. `0:0` for errors that
+    /// have no place in the source, such as I/O errors and errors from
+    /// deserializing a `Value`.
     pub position: Position,
+    /// The region the error concerns: the value a visitor rejected, or else
+    /// the token the cursor stopped at.
+    pub span: Span,
+    /// Where in the document the error occurred, written like the paths of a
+    /// `SourceMap`, e.g. `keys.49[2]`. Empty at the root.
+    pub path: String,
+    /// The innermost struct or enum the error occurred in.
+    pub outer: Option<String>,
+    /// Where the construct that an error such as a missing comma or closing
+    /// bracket concerns was opened.
+    pub opened_at: Option<Position>,
+    /// The file or other source the error occurred in, if it was named.
+    pub file: Option<String>,
+}
+
+/// An error in one of the documents of a stream, with the index of that
+/// document counting from zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentError {
+    pub document: usize,
+    pub error: SpannedError,
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub type SpannedResult<T> = std::result::Result<T, SpannedError>;
 
+// A new variant takes the next free code in `Error::code`
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Error {
@@ -71,12 +102,16 @@ pub enum Error {
         expected: &'static [&'static str],
         found: String,
         outer: Option<String>,
+        /// The expected name `found` is most likely a misspelling of.
+        suggestion: Option<&'static str>,
     },
 
     NoSuchStructField {
         expected: &'static [&'static str],
         found: String,
         outer: Option<String>,
+        /// The expected name `found` is most likely a misspelling of.
+        suggestion: Option<&'static str>,
     },
 
     MissingStructField {
@@ -92,16 +127,368 @@ pub enum Error {
     InvalidIdentifier(String),
     SuggestRawIdentifier(String),
     ExpectedRawValue,
+
+    // A limit of the `Limits` given to the deserializer was exceeded
+    DepthLimitExceeded(usize),
+    InputTooLarge(usize),
+    StringTooLong(usize),
+    CollectionTooLong(usize),
+    TooManyValues(usize),
+
+    NoSuchExtension(String),
+    FloatOutOfBounds,
+
+    /// An error from deserializing a `Value`, with the path to where it occurred.
+    AtPath {
+        path: String,
+        error: Box<Error>,
+    },
 }
 
 impl fmt::Display for SpannedError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if (self.position == Position { line: 0, col: 0 }) {
-            write!(f, "{}", self.code)
+        let start = self.span.start;
+
+        match (&self.file, start == Position { line: 0, col: 0 }) {
+            (None, true) => write!(f, "{}", self.code)?,
+            (None, false) => write!(f, "{}: {}", start, self.code)?,
+            (Some(file), true) => write!(f, "{}: {}", file, self.code)?,
+            (Some(file), false) => write!(f, "{}:{}: {}", file, start, self.code)?,
+        }
+
+        if !self.path.is_empty() {
+            write!(f, " at `{}`", self.path)?;
+        }
+
+        match self.outer {
+            Some(ref outer) if !self.code.names_outer() => write!(f, " in `{}`", outer),
+            _ => Ok(()),
+        }
+    }
+}
+
+const RED: &str = "1;31";
+const BLUE: &str = "1;34";
+const BOLD: &str = "1";
+
+impl SpannedError {
+    /// Names the file or other source the error occurred in, e.g. for an
+    /// error from `from_reader`.
+    pub fn in_file(self, file: impl Into<String>) -> Self {
+        SpannedError { file: Some(file.into()), ..self }
+    }
+
+    /// Renders the error for people to read: the line it occurred on with the
+    /// offending token underlined, where the enclosing construct was opened
+    /// and a hint on how to fix it.
+    pub fn render(&self, source: &str, filename: &str) -> String {
+        self.render_with(source, filename, false)
+    }
+
+    /// Like `render`, but coloured with ANSI escape codes for terminals.
+    pub fn render_colored(&self, source: &str, filename: &str) -> String {
+        self.render_with(source, filename, true)
+    }
+
+    fn render_with(&self, source: &str, filename: &str, colored: bool) -> String {
+        let paint = |code: &str, text: &str| if colored {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_owned()
+        };
+
+        let mut out = format!("{}: {}\n", paint(RED, "error"), self.code);
+
+        // Errors that did not come from the source, e.g. reading it
+        if self.span.start.line == 0 {
+            out += &format!("{} {}\n", paint(BLUE, "-->"), filename);
+            return out;
+        }
+
+        let start = self.span.start;
+        let last_line = self.opened_at.map_or(0, |opened| opened.line).max(start.line);
+        let pad = " ".repeat(last_line.to_string().len());
+        let bar = paint(BLUE, "|");
+
+        // Underline the span up to the end of its first line
+        let len = if self.span.end.line == start.line { self.span.end.col.saturating_sub(start.col) } else { usize::MAX };
+
+        out += &format!("{}{} {}:{}\n", pad, paint(BLUE, "-->"), filename, start);
+        out += &format!("{} {}\n", pad, bar);
+        out += &snippet(source, start, len, &pad, &paint(BLUE, &start.line.to_string()), &bar, &|len| {
+            paint(RED, &"^".repeat(len))
+        });
+
+        if let Some(opened) = self.opened_at {
+            out += &format!("{} {}\n", pad, bar);
+            out += &snippet(source, opened, 1, &pad, &paint(BLUE, &opened.line.to_string()), &bar, &|_| {
+                paint(BLUE, "- opened here")
+            });
+        }
+
+        if let Some(help) = self.help(source) {
+            out += &format!("{} {} {}: {}\n", pad, paint(BLUE, "="), paint(BOLD, "help"), help);
+        }
+
+        out
+    }
+
+    /// Renders the error for tools as a single line of JSON, so that several
+    /// errors make a JSON Lines stream. It holds the stable `code`, the
+    /// `message`, the `span` with both ends as line, char column, byte offset
+    /// and UTF-16 column, the `path`, the `file`, the enclosing struct or enum as `outer`
+    /// and the names `suggestions` offers instead of the one found.
+    pub fn render_json(&self) -> String {
+        let position = |position: Position, offset: usize, utf16_col: usize| format!(
+            "{{\"line\":{},\"col\":{},\"offset\":{},\"utf16_col\":{}}}",
+            position.line, position.col, offset, utf16_col,
+        );
+        let suggestions = self.code.suggestions().iter().map(|s| json_string(s)).collect::<Vec<_>>();
+
+        format!(
+            "{{\"code\":\"{}\",\"message\":{},\"span\":{{\"start\":{},\"end\":{}}},\"path\":{},\"file\":{},\"outer\":{},\"suggestions\":[{}]}}",
+            self.code.code(),
+            json_string(&self.code.to_string()),
+            position(self.span.start, self.span.start_offset, self.span.start_utf16_col),
+            position(self.span.end, self.span.end_offset, self.span.end_utf16_col),
+            json_string(&self.path),
+            self.file.as_deref().map_or_else(|| "null".to_owned(), json_string),
+            self.outer.as_deref().map_or_else(|| "null".to_owned(), json_string),
+            suggestions.join(","),
+        )
+    }
+
+    fn help(&self, source: &str) -> Option<String> {
+        let closes = matches!(self.code,
+            Error::Eof | Error::ExpectedMapEnd | Error::ExpectedArrayEnd | Error::ExpectedStructEnd | Error::ExpectedOptionEnd
+        );
+
+        match self.opened_at {
+            Some(opened) if closes => {
+                let line = source.lines().nth(opened.line - 1)?;
+                let bracket = line.chars().nth(opened.col - 1)?;
+                let closing = match bracket {
+                    '{' => '}',
+                    '[' => ']',
+                    _ => ')',
+                };
+
+                Some(format!("close the `{}` on line {} with `{}`", bracket, opened.line, closing))
+            }
+            _ => match self.code {
+                Error::NoSuchEnumVariant { suggestion: Some(suggestion), .. }
+                | Error::NoSuchStructField { suggestion: Some(suggestion), .. } => {
+                    Some(format!("did you mean `{}`?", suggestion))
+                }
+                _ => self.code.help().map(String::from),
+            },
+        }
+    }
+}
+
+/// One line of the source with a gutter, followed by a marker under the
+/// `len` chars at `position`, made by `marker` from their number.
+fn snippet(source: &str, position: Position, len: usize, pad: &str, number: &str, bar: &str, marker: &dyn Fn(usize) -> String) -> String {
+    let line = source.lines().nth(position.line - 1).unwrap_or("");
+    let start = line.char_indices().nth(position.col - 1).map_or(line.len(), |(i, _)| i);
+
+    // Keep tabs, so the marker lines up however wide they are shown
+    let indent = line[..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+    let token = len.min(line[start..].chars().count()).max(1);
+
+    let number_pad = " ".repeat(pad.len() - position.line.to_string().len());
+    format!("{}{} {} {}\n{} {} {}{}\n", number_pad, number, bar, line, pad, bar, indent, marker(token))
+}
+
+/// Quotes and escapes a string as JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "document {}: {}", self.document, self.error)
+    }
+}
+
+impl Error {
+    /// The stable code of the error, e.g. `Z0004` for an unexpected end of
+    /// input. Unlike the message, a code never changes once assigned and is
+    /// never reused, so tools can match on it. An error at a path has the
+    /// code of the error it wraps.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::Io(_) => "Z0001",
+            Error::Message(_) => "Z0002",
+            Error::Base64Error(_) => "Z0003",
+            Error::Eof => "Z0004",
+            Error::ExpectedArray => "Z0005",
+            Error::ExpectedArrayEnd => "Z0006",
+            Error::ExpectedAttribute => "Z0007",
+            Error::ExpectedAttributeEnd => "Z0008",
+            Error::ExpectedBoolean => "Z0009",
+            Error::ExpectedComma => "Z0010",
+            Error::ExpectedChar => "Z0011",
+            Error::ExpectedFloat => "Z0012",
+            Error::FloatUnderscore => "Z0013",
+            Error::ExpectedInteger => "Z0014",
+            Error::ExpectedOption => "Z0015",
+            Error::ExpectedOptionEnd => "Z0016",
+            Error::ExpectedMap => "Z0017",
+            Error::ExpectedMapSeparator => "Z0018",
+            Error::ExpectedMapEnd => "Z0019",
+            Error::ExpectedString => "Z0020",
+            Error::ExpectedStringEnd => "Z0021",
+            Error::ExpectedIdentifier => "Z0022",
+            Error::ExpectedDifferentStructName { .. } => "Z0023",
+            Error::ExpectedStruct => "Z0024",
+            Error::ExpectedNamedStruct(_) => "Z0025",
+            Error::ExpectedStructEnd => "Z0026",
+            Error::ExpectedTupleStruct => "Z0027",
+            Error::ExpectedUnit => "Z0028",
+            Error::InvalidEscape(_) => "Z0029",
+            Error::IntegerOutOfBounds => "Z0030",
+            Error::UnclosedBlockComment => "Z0031",
+            Error::UnderscoreAtBeginning => "Z0032",
+            Error::UnexpectedByte(_) => "Z0033",
+            Error::Utf8Error(_) => "Z0034",
+            Error::TrailingCharacters => "Z0035",
+            Error::InvalidValueForType { .. } => "Z0036",
+            Error::ExpectedDifferentLength { .. } => "Z0037",
+            Error::NoSuchEnumVariant { .. } => "Z0038",
+            Error::NoSuchStructField { .. } => "Z0039",
+            Error::MissingStructField { .. } => "Z0040",
+            Error::DuplicateStructField { .. } => "Z0041",
+            Error::InvalidIdentifier(_) => "Z0042",
+            Error::SuggestRawIdentifier(_) => "Z0043",
+            Error::ExpectedRawValue => "Z0044",
+            Error::DepthLimitExceeded(_) => "Z0045",
+            Error::InputTooLarge(_) => "Z0046",
+            Error::StringTooLong(_) => "Z0047",
+            Error::CollectionTooLong(_) => "Z0048",
+            Error::TooManyValues(_) => "Z0049",
+            Error::NoSuchExtension(_) => "Z0050",
+            Error::FloatOutOfBounds => "Z0051",
+            Error::AtPath { ref error, .. } => error.code(),
+        }
+    }
+
+    /// The names the error suggests instead of the one found.
+    pub fn suggestions(&self) -> Vec<&'static str> {
+        match *self {
+            Error::NoSuchEnumVariant { suggestion, .. }
+            | Error::NoSuchStructField { suggestion, .. } => suggestion.into_iter().collect(),
+            Error::AtPath { ref error, .. } => error.suggestions(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// A hint on how to fix the error, where there is a common one.
+    pub fn help(&self) -> Option<&'static str> {
+        Some(match *self {
+            Error::ExpectedComma => "separate the entries with a comma `,`",
+            Error::ExpectedMapSeparator => "separate each key from its value with a colon `:`",
+            Error::ExpectedBoolean => "write `true` or `false`",
+            Error::ExpectedChar => "chars are written in single quotes, like `'c'`",
+            Error::ExpectedString => "strings are written in double quotes, like `\"text\"`",
+            Error::ExpectedStringEnd => "close the string with `\"`",
+            Error::ExpectedUnit => "write the unit value as `()`",
+            Error::TrailingCharacters => "a document holds a single value, read several with `StreamDeserializer`",
+            _ => return None,
+        })
+    }
+
+    /// Names the struct or enum an error about its fields or variants
+    /// occurred in, unless an inner one was named already.
+    pub(crate) fn with_outer(mut self, name: &str) -> Self {
+        match self {
+            Error::NoSuchEnumVariant { ref mut outer, .. }
+            | Error::NoSuchStructField { ref mut outer, .. }
+            | Error::MissingStructField { ref mut outer, .. }
+            | Error::DuplicateStructField { ref mut outer, .. } => {
+                outer.get_or_insert_with(|| name.to_owned());
+                self
+            }
+            Error::AtPath { path, error } => Error::AtPath { path, error: Box::new(error.with_outer(name)) },
+            _ => self,
+        }
+    }
+
+    /// Puts a map key or a `[index]` in front of the path of an error from
+    /// deserializing a `Value`, on its way out of the tree.
+    pub(crate) fn at(self, segment: String) -> Self {
+        let (path, error) = match self {
+            Error::AtPath { path, error } => (path, error),
+            e => (String::new(), Box::new(e)),
+        };
+
+        let path = if path.is_empty() || path.starts_with('[') {
+            segment + &path
         } else {
-            write!(f, "{}: {}", self.position, self.code)
+            segment + "." + &path
+        };
+
+        Error::AtPath { path, error }
+    }
+
+    /// Whether the message already says which struct or enum it is about.
+    fn names_outer(&self) -> bool {
+        match *self {
+            Error::NoSuchEnumVariant { ref outer, .. }
+            | Error::NoSuchStructField { ref outer, .. }
+            | Error::MissingStructField { ref outer, .. }
+            | Error::DuplicateStructField { ref outer, .. } => outer.is_some(),
+            _ => false,
         }
     }
+
+    /// Whether the error comes from a visitor rejecting a value that parsed
+    /// fine, or from a value over a limit, so the value is what it should
+    /// point at.
+    pub(crate) fn concerns_value(&self) -> bool {
+        matches!(self,
+            Error::Message(_)
+                | Error::InvalidValueForType { .. }
+                | Error::ExpectedDifferentLength { .. }
+                | Error::NoSuchEnumVariant { .. }
+                | Error::NoSuchStructField { .. }
+                | Error::MissingStructField { .. }
+                | Error::DuplicateStructField { .. }
+                | Error::StringTooLong(_)
+                | Error::CollectionTooLong(_)
+                | Error::AtPath { .. }
+        )
+    }
+
+    /// Whether the error is about a construct left open, so its opening is
+    /// worth pointing out.
+    pub(crate) fn concerns_opening(&self) -> bool {
+        matches!(self,
+            Error::Eof
+                | Error::ExpectedComma
+                | Error::ExpectedMapSeparator
+                | Error::ExpectedMapEnd
+                | Error::ExpectedArrayEnd
+                | Error::ExpectedStructEnd
+                | Error::ExpectedOptionEnd
+        )
+    }
 }
 
 impl fmt::Display for Error {
@@ -110,12 +497,12 @@ impl fmt::Display for Error {
             Error::Io(ref s) => f.write_str(s),
             Error::Message(ref s) => f.write_str(s),
             Error::Base64Error(ref e) => fmt::Display::fmt(e, f),
-            Error::Eof => f.write_str("Unexpected end of RON"),
+            Error::Eof => f.write_str("Unexpected end of input"),
             Error::ExpectedArray => f.write_str("Expected opening `[`"),
             Error::ExpectedArrayEnd => f.write_str("Expected closing `]`"),
-            Error::ExpectedAttribute => f.write_str("Expected an `#![enable(...)]` attribute"),
+            Error::ExpectedAttribute => f.write_str("Expected an `#![enable(...)]` or `#![disable(...)]` pragma"),
             Error::ExpectedAttributeEnd => {
-                f.write_str("Expected closing `)]` after the enable attribute")
+                f.write_str("Expected closing `)]` after the extensions of the pragma")
             }
             Error::ExpectedBoolean => f.write_str("Expected boolean"),
             Error::ExpectedComma => f.write_str("Expected comma"),
@@ -131,11 +518,11 @@ impl fmt::Display for Error {
             Error::ExpectedMapEnd => f.write_str("Expected map closing, either bracket `}` or semi-colon `;` for nested cavetta construct"),
             Error::ExpectedDifferentStructName { expected, ref found } => 
                 write!(f, "Expected struct '{}' but found '{}'", expected, found),
-            Error::ExpectedStruct => f.write_str("Expected opening `(`"),
+            Error::ExpectedStruct => f.write_str("Expected opening `{`"),
             Error::ExpectedNamedStruct(name) => 
-                write!(f, "Expected opening `(` for struct '{}'", name),
+                write!(f, "Expected opening `{{` for struct '{}'", name),
             Error::ExpectedTupleStruct => f.write_str("Expected tuple struct"),
-            Error::ExpectedStructEnd => f.write_str("Expected closing `)`"),
+            Error::ExpectedStructEnd => f.write_str("Expected closing `}`"),
             Error::ExpectedUnit => f.write_str("Expected unit"),
             Error::ExpectedString => f.write_str("Expected string"),
             Error::ExpectedStringEnd => f.write_str("Expected end of string"),
@@ -164,7 +551,7 @@ impl fmt::Display for Error {
                 f.write_str(" instead")
             }
 
-            Error::NoSuchEnumVariant { expected, ref found, ref outer } => {
+            Error::NoSuchEnumVariant { expected, ref found, ref outer, suggestion } => {
                 f.write_str("Unexpected ")?;
 
                 if outer.is_none() {
@@ -174,34 +561,30 @@ impl fmt::Display for Error {
                 write!(f, "variant named {}", Identifier(found))?;
 
                 if let Some(outer) = outer {
-                    write!(f, "in enum {}", Identifier(outer))?;
+                    write!(f, " in enum {}", Identifier(outer))?;
                 }
 
-                write!(
-                    f,
-                    ", {}",
-                    OneOf {
-                        alts: expected,
-                        none: "variants"
-                    }
-                )
+                write!(f, ", {}", OneOf { alts: expected, none: "variants" })?;
+
+                match suggestion {
+                    Some(suggestion) => write!(f, "; did you mean {}?", Identifier(suggestion)),
+                    None => Ok(()),
+                }
             }
 
-            Error::NoSuchStructField { expected, ref found, ref outer } => {
+            Error::NoSuchStructField { expected, ref found, ref outer, suggestion } => {
                 write!(f, "Unexpected field named {}", Identifier(found))?;
 
                 if let Some(outer) = outer {
-                    write!(f, "in {}", Identifier(outer))?;
+                    write!(f, " in {}", Identifier(outer))?;
                 }
 
-                write!(
-                    f,
-                    ", {}",
-                    OneOf {
-                        alts: expected,
-                        none: "fields"
-                    }
-                )
+                write!(f, ", {}", OneOf { alts: expected, none: "fields" })?;
+
+                match suggestion {
+                    Some(suggestion) => write!(f, "; did you mean {}?", Identifier(suggestion)),
+                    None => Ok(()),
+                }
             }
 
             Error::MissingStructField { field, ref outer } => {
@@ -228,12 +611,27 @@ impl fmt::Display for Error {
                 "Found invalid std identifier `{}`, try the raw identifier `r#{}` instead",
                 identifier, identifier
             ),
-            Error::ExpectedRawValue => f.write_str("Expected a `ron::value::RawValue`"),
+            Error::ExpectedRawValue => f.write_str("Expected a `zmerald::RawValue`"),
+            Error::DepthLimitExceeded(limit) => write!(f, "Nesting is deeper than the limit of {}", limit),
+            Error::InputTooLarge(limit) => write!(f, "Input is larger than the limit of {} bytes", limit),
+            Error::StringTooLong(limit) => write!(f, "String is longer than the limit of {} bytes", limit),
+            Error::CollectionTooLong(limit) => write!(f, "Collection has more than the limit of {} entries", limit),
+            Error::TooManyValues(limit) => write!(f, "Document has more than the limit of {} values", limit),
+            Error::NoSuchExtension(ref found) => write!(
+                f,
+                "Unknown extension {}, {}",
+                Identifier(found),
+                OneOf { alts: crate::de::Extension::NAMES, none: "extensions" },
+            ),
+            Error::FloatOutOfBounds => f.write_str("Float is out of bounds"),
+            Error::AtPath { ref path, ref error } => write!(f, "{} at `{}`", error, path),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A place in the source. Lines and columns count from one, and columns
+/// count chars rather than bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
 pub struct Position {
     pub line: usize,
     pub col: usize,
@@ -245,6 +643,24 @@ impl fmt::Display for Position {
     }
 }
 
+/// A region of the source, with the byte offsets of both ends and their
+/// columns in UTF-16 code units, as editors using LSP count them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub start_utf16_col: usize,
+    pub end_utf16_col: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 impl ser::Error for Error {
     #[cold]
     fn custom<T: fmt::Display>(msg: T) -> Self {
@@ -317,6 +733,7 @@ impl de::Error for Error {
             expected,
             found: variant.to_string(),
             outer: None,
+            suggestion: suggest(variant, expected),
         }
     }
 
@@ -326,6 +743,7 @@ impl de::Error for Error {
             expected,
             found: field.to_string(),
             outer: None,
+            suggestion: suggest(field, expected),
         }
     }
 
@@ -336,12 +754,12 @@ impl de::Error for Error {
 
     #[cold]
     fn duplicate_field(field: &'static str) -> Self {
-        println!("field ĦAQQQQQ: {}", field);
         Error::DuplicateStructField { field, outer: None }
     }
 }
 
 impl StdError for SpannedError {}
+impl StdError for DocumentError {}
 impl StdError for Error {}
 
 impl From<Utf8Error> for Error {
@@ -367,16 +785,94 @@ impl From<io::Error> for SpannedError {
         SpannedError {
             code: e.into(),
             position: Position { line: 0, col: 0 },
+            span: Span::default(),
+            path: String::new(),
+            outer: None,
+            opened_at: None,
+            file: None,
         }
     }
 }
 
+impl From<Error> for SpannedError {
+    /// Gives an error from deserializing a `Value` the zero position, taking
+    /// its path out of `Error::AtPath`.
+    fn from(e: Error) -> Self {
+        let (path, code) = match e {
+            Error::AtPath { path, error } => (path, *error),
+            e => (String::new(), e),
+        };
+
+        SpannedError {
+            code,
+            position: Position { line: 0, col: 0 },
+            span: Span::default(),
+            path,
+            outer: None,
+            opened_at: None,
+            file: None,
+        }
+    }
+}
+
+impl From<DocumentError> for SpannedError {
+    fn from(e: DocumentError) -> Self {
+        e.error
+    }
+}
+
 impl From<SpannedError> for Error {
     fn from(e: SpannedError) -> Self {
         e.code
     }
 }
 
+/// The expected name that `found` most likely misspells: one that differs
+/// only in case, `_` and `-`, or else the closest one by edit distance, if it
+/// is within a third of the name's length.
+fn suggest(found: &str, expected: &'static [&'static str]) -> Option<&'static str> {
+    let normalize = |s: &str| s.chars().filter(|&c| c != '_' && c != '-').flat_map(char::to_lowercase).collect::<Vec<_>>();
+    let found = normalize(found);
+
+    if let Some(&name) = expected.iter().find(|name| normalize(name) == found) {
+        return Some(name);
+    }
+
+    let limit = (found.len() / 3).max(1);
+
+    expected.iter()
+        .map(|&name| (edit_distance(&found, &normalize(name)), name))
+        .filter(|&(distance, _)| distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, name)| name)
+}
+
+/// The Levenshtein distance, counting an adjacent swap as one edit.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // Three rows of the usual table, for the swap of two chars
+    let mut before = vec![0; b.len() + 1];
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
 struct OneOf {
     alts: &'static [&'static str],
     none: &'static str,
@@ -1,6 +1,16 @@
 pub mod ser;
+pub use ser::fingerprint;
 pub mod de;
-pub use de::{ from_str, from_bytes, from_reader }; 
+pub use de::{ from_str, from_bytes, from_reader, from_reader_with_options, from_path, from_str_with_source_map, from_str_with_warnings, from_str_with_limits, from_str_with_options, check, DeserializerOptions, Extension, Limits, StreamDeserializer };
 pub mod error;
 pub mod parse;
-pub mod value;
\ No newline at end of file
+pub mod value;
+pub mod spanned;
+pub use spanned::Spanned;
+pub use value::RawValue;
+pub mod source_map;
+pub use source_map::SourceMap;
+pub mod comment_map;
+pub use comment_map::CommentMap;
+pub mod warning;
+pub use warning::Warning;
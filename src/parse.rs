@@ -1,6 +1,6 @@
 #![allow(clippy::identity_op)]
 
-use crate::error::{ Error, SpannedError, Position, Result, SpannedResult };
+use crate::error::{ Error, SpannedError, Position, Result, Span, SpannedResult };
 
 use std::{
     char::from_u32 as char_from_u32,
@@ -84,10 +84,26 @@ const fn is_whitespace_char(c: u8) -> bool {
     ENCODINGS[c as usize] & WHITESPACE_CHAR != 0
 }
 
-const fn is_reserved_char(c: u8) -> bool {
+pub const fn is_reserved_char(c: u8) -> bool {
     ENCODINGS[c as usize] & RESERVED_CHAR != 0
 }
 
+/// Whether `s` can be written without quotes, to be read back as is by
+/// `Bytes::string`. Its first char must not start anything else
+/// `deserialize_any` reads, like an identifier, a number or a comment, so an
+/// untyped `Value` reads it back as a string too.
+pub fn is_bare_string(s: &str) -> bool {
+    s.bytes().next().is_some_and(is_bare_string_first_char)
+        && s.bytes().all(|b| !is_reserved_char(b) && !is_whitespace_char(b) && b != b'"' && b != b'\\' && b != b'#')
+        && !s.chars().any(|c| c.is_control() || c.is_whitespace())
+}
+
+/// Whether `deserialize_any` reads a bare string starting with `c` as a
+/// string.
+pub const fn is_bare_string_first_char(c: u8) -> bool {
+    ENCODINGS[c as usize] == _____ && c > b' ' && c != 0x7f && !matches!(c, b'"' | b'#' | b'/' | b'\\')
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum AnyNum {
     F32(f32),
@@ -106,30 +122,101 @@ pub enum AnyNum {
 pub struct Bytes<'a> {
     bytes: &'a [u8],
     cursor: Position,
+    offset: usize,
+    utf16_col: usize,
 }
 
 pub(crate) type LargeUInt = u64;
 pub(crate) type LargeSInt = i64;
 
 impl<'a> Bytes<'a> {
+    #[allow(clippy::result_large_err)]
     pub fn new(bytes: &'a [u8]) -> SpannedResult<Self> {
-        let mut b = Bytes {
-            bytes,
-            cursor: Position { line: 1, col: 1 }
-        };
+        let mut b = Bytes::start(bytes);
 
-        b.skip_ws().map_err(|e| b.span_error(e))?;
+        b.skip_ws_before_pragma().map_err(|e| b.span_error(e))?;
         Ok(b)
     }
 
+    /// The very start of `bytes`, with nothing skipped.
+    pub fn start(bytes: &'a [u8]) -> Self {
+        Bytes {
+            bytes,
+            cursor: Position { line: 1, col: 1 },
+            offset: 0,
+            utf16_col: 1,
+        }
+    }
+
+    /// Fails if more than `limit` bytes are left, pointing at the first byte
+    /// beyond it without reading any further.
+    #[allow(clippy::result_large_err)]
+    pub fn check_input_size(&self, limit: usize) -> SpannedResult<()> {
+        if self.bytes.len() <= limit {
+            return Ok(());
+        }
+
+        let mut excess = Bytes { bytes: &self.bytes[..=limit], ..*self };
+        let _ = excess.advance(limit);
+
+        Err(excess.span_error(Error::InputTooLarge(limit)))
+    }
+
     pub fn append(&mut self, bytes: &'a [u8]) {
         [self.bytes, bytes].concat();
     }
 
+    pub fn position(&self) -> Position {
+        self.cursor
+    }
+
+    /// The number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The column of the cursor in UTF-16 code units, as editors count them.
+    pub fn utf16_col(&self) -> usize {
+        self.utf16_col
+    }
+
+    /// The span from where `start` was to the cursor.
+    pub fn span_since(&self, start: &Bytes<'_>) -> Span {
+        Span {
+            start: start.cursor,
+            end: self.cursor,
+            start_offset: start.offset,
+            end_offset: self.offset,
+            start_utf16_col: start.utf16_col,
+            end_utf16_col: self.utf16_col,
+        }
+    }
+
+    /// The span of the token at the cursor, which is a single char if it is
+    /// reserved and empty at the end of the input.
+    pub fn token_span(&self) -> Span {
+        let mut end = *self;
+        let len = match self.peek() {
+            Some(b) if is_whitespace_char(b) || is_reserved_char(b) => 1,
+            Some(_) => self.bytes.iter().take_while(|&&b| !is_whitespace_char(b) && !is_reserved_char(b)).count(),
+            None => 0,
+        };
+
+        let _ = end.advance(len);
+        end.span_since(self)
+    }
+
     pub fn span_error(&self, code: Error) -> SpannedError {
+        let span = self.token_span();
+
         SpannedError {
             code,
-            position: self.cursor,
+            position: span.start,
+            span,
+            path: String::new(),
+            outer: None,
+            opened_at: None,
+            file: None,
         }
     }
 
@@ -142,14 +229,20 @@ impl<'a> Bytes<'a> {
     }
 
     pub fn advance_single(&mut self) -> Result<()> {
-        if self.peek_or_eof()? == b'\n' {
+        let byte = self.peek_or_eof()?;
+
+        if byte == b'\n' {
             self.cursor.line += 1;
             self.cursor.col = 1;
-        } else {
+            self.utf16_col = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // Columns count chars, so continuation bytes do not move them
             self.cursor.col += 1;
+            self.utf16_col += if byte >= 0xF0 { 2 } else { 1 };
         }
 
         self.bytes = &self.bytes[1..];
+        self.offset += 1;
 
         Ok(())
     }
@@ -234,7 +327,7 @@ impl<'a> Bytes<'a> {
             }
         }
 
-        let bytes_backup = self.bytes;
+        let backup = *self;
 
         let first_byte = self.peek_or_eof()?;
         let is_signed = first_byte == b'-' || first_byte == b'+';
@@ -270,7 +363,7 @@ impl<'a> Bytes<'a> {
                         }
                     }
                     Err(_) => {
-                        self.bytes = bytes_backup;
+                        *self = backup;
                         any_float(self.float::<f64>()?)
                     }
                 }
@@ -288,7 +381,7 @@ impl<'a> Bytes<'a> {
                         }
                     }
                     Err(_) => {
-                        self.bytes = bytes_backup;
+                        *self = backup;
                         any_float(self.float::<f64>()?)
                     }
                 }
@@ -306,7 +399,7 @@ impl<'a> Bytes<'a> {
         }
     }
 
-    pub fn bytes(&self) -> &[u8] {
+    pub fn bytes(&self) -> &'a [u8] {
         self.bytes
     }
 
@@ -318,7 +411,7 @@ impl<'a> Bytes<'a> {
             let i = self.bytes.iter().take_while(|&&b | !is_reserved_char(b) && !is_whitespace_char(b)).count();
             let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
             self.consume(s);
-            Ok(s.chars().next().unwrap())
+            s.chars().next().ok_or(Error::ExpectedChar)
         }  
     }
 
@@ -394,6 +487,21 @@ impl<'a> Bytes<'a> {
         self.eat_byte().map(|c| c != b':')
     }
 
+    /// Whether whitespace or a comment comes next.
+    pub fn check_ws(&self) -> bool {
+        self.peek().is_some_and(|b| is_whitespace_char(b) || b == b'#')
+    }
+
+    /// Whether `(`, a single value and `)` come next, which no value but a
+    /// newtype struct in its explicit form reads as.
+    pub fn check_wrapped_value(mut self) -> bool {
+        self.consume("(")
+            && self.skip_ws().is_ok()
+            && self.raw_value().is_ok()
+            && self.skip_ws().is_ok()
+            && self.consume(")")
+    }
+
     pub fn consume_ident(&mut self, ident: &str) -> bool {
         if self.check_ident(ident) {
             let _ = self.advance(ident.len());
@@ -437,29 +545,6 @@ impl<'a> Bytes<'a> {
         }
     }
 
-    pub fn dollar(&mut self) -> Result<Option<(String, String)>> {
-        todo!()
-        // if self.consume("$") {
-        //     self.skip_ws()?;
-
-        //     let i = self.bytes.iter().take_while(|&&b | b!='=' as u8).count();
-        //     let name = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
-        //     if !name.is_empty() {
-        //         self.consume(name);
-        //     }
-
-        //     let i = self.bytes.iter().take_while(|&&b | b!=';' as u8).count();
-        //     let value = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
-        //     if !value.is_empty() {
-        //         self.consume(value);
-        //     }
-
-        //     return Ok(Some((String::from(name), String::from(value))));
-        // }
-
-        // Ok(None)
-    }
-
     pub fn eat_byte(&mut self) -> Result<u8> {
         let peek = self.peek_or_eof()?;
         let _ = self.advance_single();
@@ -472,10 +557,10 @@ impl<'a> Bytes<'a> {
             .and_then(|b| if b == byte { Ok(()) } else { Err(error) })
     }
 
-    pub fn float<T>(&mut self) -> Result<T> where T: FromStr {
+    pub fn float<T>(&mut self) -> Result<T> where T: FromStr + Into<f64> + Copy {
         for literal in &["inf", "+inf", "-inf", "NaN", "+NaN", "-NaN"] {
             if self.consume_ident(literal) {
-                return FromStr::from_str(literal).map_err(|_| unreachable!()); // must not fail
+                return FromStr::from_str(literal).map_err(|_| Error::ExpectedFloat);
             }
         }
 
@@ -489,7 +574,12 @@ impl<'a> Bytes<'a> {
         // }
 
         let s = unsafe { from_utf8_unchecked(&self.bytes[0..num_bytes]) };
-        let res = FromStr::from_str(s).map_err(|_| Error::ExpectedFloat);
+        let res = match T::from_str(s) {
+            // Only `inf` may read as infinity, not a number too large for `T`
+            Ok(f) if f.into().is_infinite() => Err(Error::FloatOutOfBounds),
+            Ok(f) => Ok(f),
+            Err(_) => Err(Error::ExpectedFloat),
+        };
 
         let _ = self.advance(num_bytes);
 
@@ -581,6 +671,110 @@ impl<'a> Bytes<'a> {
         Ok(None)
     } 
 
+    /// Skips over the next value without interpreting it and returns its
+    /// source, including any comments inside it.
+    pub fn raw_value(&mut self) -> Result<&'a str> {
+        let start = self.bytes;
+
+        match self.peek_or_eof()? {
+            b'"' => self.skip_raw_string()?,
+            b'\'' => self.skip_raw_char()?,
+            b'(' | b'[' | b'{' => self.skip_raw_group()?,
+            _ => {
+                let token = self.bytes.iter()
+                    .take_while(|&&b| !is_reserved_char(b) && !is_whitespace_char(b))
+                    .count();
+
+                if token == 0 {
+                    return Err(Error::ExpectedRawValue);
+                }
+
+                let is_ident = is_ident_first_char(self.bytes[0]);
+                self.advance(token)?;
+
+                // Struct names, enum variants and `Some` may be followed by a group
+                let mut lookahead = *self;
+                lookahead.skip_ws()?;
+
+                if is_ident && matches!(lookahead.peek(), Some(b'(') | Some(b'{')) {
+                    *self = lookahead;
+                    self.skip_raw_group()?;
+                }
+            }
+        }
+
+        let len = start.len() - self.bytes.len();
+        from_utf8(&start[..len]).map_err(Error::from)
+    }
+
+    fn skip_raw_group(&mut self) -> Result<()> {
+        let mut closers = Vec::new();
+        let mut at_token_start = true;
+
+        loop {
+            let byte = self.peek_or_eof()?;
+
+            match byte {
+                b'(' => closers.push(b')'),
+                b'[' => closers.push(b']'),
+                b'{' => closers.push(b'}'),
+                b')' | b']' | b'}' if closers.pop() != Some(byte) => {
+                    return Err(Error::ExpectedRawValue);
+                }
+                b'"' => {
+                    self.skip_raw_string()?;
+                    at_token_start = false;
+                    continue;
+                }
+                b'\'' => {
+                    self.skip_raw_char()?;
+                    at_token_start = false;
+                    continue;
+                }
+                b'#' if at_token_start => {
+                    self.skip_comment()?;
+                    continue;
+                }
+                _ => (),
+            }
+
+            self.advance_single()?;
+            at_token_start = is_whitespace_char(byte) || is_reserved_char(byte);
+
+            if closers.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_raw_string(&mut self) -> Result<()> {
+        self.advance_single()?;
+
+        loop {
+            match self.eat_byte().map_err(|_| Error::ExpectedStringEnd)? {
+                b'\\' => {
+                    self.eat_byte().map_err(|_| Error::ExpectedStringEnd)?;
+                }
+                b'"' => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
+    fn skip_raw_char(&mut self) -> Result<()> {
+        self.advance_single()?;
+
+        loop {
+            match self.eat_byte().map_err(|_| Error::ExpectedChar)? {
+                b'\\' => {
+                    self.eat_byte().map_err(|_| Error::ExpectedChar)?;
+                }
+                b'\'' => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
     pub fn next_bytes_contained_in(&self, allowed: fn(u8) -> bool) -> usize {
         self.bytes.iter().take_while(|&&b| allowed(b)).count()
     }
@@ -604,6 +798,20 @@ impl<'a> Bytes<'a> {
         }
     }
 
+    /// Like `skip_ws`, but stops at a `#![` pragma rather than skipping it as
+    /// a comment.
+    pub fn skip_ws_before_pragma(&mut self) -> Result<()> {
+        loop {
+            while self.peek().is_some_and(is_whitespace_char) {
+                let _ = self.advance_single();
+            }
+
+            if self.test_for("#![") || !self.skip_comment()? {
+                return Ok(());
+            }
+        }
+    }
+
     pub fn skip_ws(&mut self) -> Result<()> {
         loop {
             while self.peek().map_or(false, is_whitespace_char) {
@@ -1,12 +1,18 @@
 use serde::{ ser, Deserialize, Serialize };
-use std::io;
+use std::collections::HashMap;
+use std::io::{ self, Write };
 
 use crate::{
+    comment_map::CommentMap,
+    de::Extension,
     error::{ Error, Result },
-    parse::{ is_ident_first_char, is_ident_other_char, LargeSInt, LargeUInt },
+    value::Value,
+    parse::{ is_bare_string, is_ident_first_char, is_ident_other_char, LargeSInt, LargeUInt },
+    source_map::unquoted,
 };
 
 mod value;
+use value::to_value;
 
 pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
 where W: io::Write, T: ?Sized + ser::Serialize {
@@ -17,7 +23,7 @@ where W: io::Write, T: ?Sized + ser::Serialize {
 pub fn to_writer_pretty<W, T>(writer: W, value: &T, config: PrettyConfig) -> Result<()>
 where W: io::Write, T: ?Sized + ser::Serialize {
     let mut s = Serializer::with_options(writer, Some(config), )?;
-    value.serialize(&mut s)
+    s.serialize_fitted(value, 0)
 }
 
 pub fn to_string<T>(value: &T) -> Result<String>
@@ -32,13 +38,204 @@ pub fn to_string_pretty<T>(value: &T, config: PrettyConfig) -> Result<String>
 where T: ?Sized + ser::Serialize {
     let mut output = Vec::new();
     let mut s = Serializer::with_options(&mut output, Some(config), )?;
-    value.serialize(&mut s)?;
+    s.serialize_fitted(value, 0)?;
+    Ok(String::from_utf8(output).expect("Ron should be utf-8"))
+}
+
+/// Writes the canonical form of a value: compact, with map keys sorted by
+/// `Value`'s `Ord` and floats normalised, so equal values are written alike.
+pub fn to_writer_canonical<W, T>(writer: W, value: &T) -> Result<()>
+where W: io::Write, T: ?Sized + ser::Serialize {
+    let mut s = Serializer::with_options(writer, None)?;
+    s.canonical = true;
+    value.serialize(&mut s)
+}
+
+pub fn to_string_canonical<T>(value: &T) -> Result<String>
+where T: ?Sized + ser::Serialize {
+    let mut output = Vec::new();
+    to_writer_canonical(&mut output, value)?;
+    Ok(String::from_utf8(output).expect("Ron should be utf-8"))
+}
+
+/// A hash of the canonical form of a value, which stays the same across
+/// runs, platforms and releases as long as the value does.
+pub fn fingerprint<T>(value: &T) -> Result<u64>
+where T: ?Sized + ser::Serialize {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    to_writer_canonical(&mut hasher, value)?;
+    Ok(hasher.0)
+}
+
+// 64 bit FNV-1a, which unlike `DefaultHasher` is specified
+struct Fnv(u64);
+
+impl io::Write for Fnv {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Like `to_string_pretty`, but writes the comments of `comments` as `#`
+/// lines above the values at their paths.
+pub fn to_string_with_comments<T>(value: &T, comments: &CommentMap, config: PrettyConfig) -> Result<String>
+where T: ?Sized + ser::Serialize {
+    let mut output = Vec::new();
+    to_writer_with_comments(&mut output, value, comments, config)?;
     Ok(String::from_utf8(output).expect("Ron should be utf-8"))
 }
 
+pub fn to_writer_with_comments<W, T>(writer: W, value: &T, comments: &CommentMap, config: PrettyConfig) -> Result<()>
+where W: io::Write, T: ?Sized + ser::Serialize {
+    let mut s = Serializer::with_options(writer, Some(config))?;
+    s.path = Some(Path::default());
+    s.comments = Some(comments.clone());
+
+    s.write_comment(true)?;
+    s.serialize_fitted(value, 0)
+}
+
+/// Writes only the struct fields of `value` that differ from those of
+/// `defaults`, going into nested structs field by field. Values in sequences,
+/// maps, options and enum variants are written whole when they differ.
+/// Fields are compared by their canonical form.
+///
+/// Reading the output into a type whose structs are `#[serde(default)]`, with
+/// `defaults` as their defaults, gives back `value`.
+pub fn to_string_diff<T>(value: &T, defaults: &T, config: PrettyConfig) -> Result<String>
+where T: ?Sized + ser::Serialize {
+    let mut output = Vec::new();
+    to_writer_diff(&mut output, value, defaults, config)?;
+    Ok(String::from_utf8(output).expect("Ron should be utf-8"))
+}
+
+pub fn to_writer_diff<W, T>(writer: W, value: &T, defaults: &T, config: PrettyConfig) -> Result<()>
+where W: io::Write, T: ?Sized + ser::Serialize {
+    let mut recorder = Serializer::with_options(io::sink(), None)?;
+    recorder.path = Some(Path::default());
+    recorder.diff = Some(Diff { defaults: HashMap::new(), recording: true, armed: Some(0) });
+    defaults.serialize(&mut recorder)?;
+
+    let mut s = Serializer::with_options(writer, Some(config))?;
+    s.path = Some(Path::default());
+    s.diff = recorder.diff.map(|diff| Diff { recording: false, armed: Some(0), ..diff });
+    s.serialize_fitted(value, 0)
+}
+
+/// Writes several values as a stream, one document after the other with a
+/// `---` line between them, to be read back by `StreamDeserializer`. Every
+/// document ends with a new line, so streams can be appended to.
+pub fn to_writer_many<W, I>(mut writer: W, values: I, config: Option<PrettyConfig>) -> Result<()>
+where W: io::Write, I: IntoIterator, I::Item: ser::Serialize {
+    let new_line = config.as_ref().map_or("\n", |config| config.new_line.as_str()).to_owned();
+
+    // One serializer for all documents, so a pragma is written once on top
+    let mut s = Serializer::with_options(&mut writer, config)?;
+
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            s.output.write_all(crate::de::DOCUMENT_MARKER.as_bytes())?;
+            s.output.write_all(new_line.as_bytes())?;
+        }
+
+        if s.pretty.is_some() {
+            s.serialize_fitted(&value, 0)?;
+        } else {
+            value.serialize(&mut s)?;
+        }
+
+        s.output.write_all(new_line.as_bytes())?;
+    }
+
+    Ok(())
+}
+
 struct Pretty {
     indent: usize,
     sequence_index: Vec<usize>,
+    // Collections deeper than this are written on one line, as they fit `max_width`
+    flat_limit: usize,
+}
+
+impl Pretty {
+    /// Whether the collection at the current indent is laid out one entry per line.
+    fn breaks(&self, config: &PrettyConfig) -> bool {
+        self.indent <= config.depth_limit.min(self.flat_limit)
+    }
+}
+
+/// Keeps track of the column the next byte is written at, and holds back
+/// what is written while capturing, e.g. map entries to be sorted.
+struct Output<W> {
+    writer: W,
+    column: usize,
+    captures: Vec<Vec<u8>>,
+}
+
+impl<W> Output<W> {
+    fn new(writer: W) -> Self {
+        Output { writer, column: 0, captures: Vec::new() }
+    }
+}
+
+impl<W: io::Write> io::Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = match self.captures.last_mut() {
+            Some(capture) => {
+                capture.extend_from_slice(buf);
+                buf.len()
+            }
+            None => self.writer.write(buf)?,
+        };
+        let buf = &buf[..written];
+
+        match buf.iter().rposition(|&b| b == b'\n') {
+            Some(line_start) => self.column = width(&buf[line_start + 1..]),
+            None => self.column += width(buf),
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Counts the width of what is written, failing once it passes `limit` or
+/// breaks the line.
+struct Measure {
+    width: usize,
+    limit: usize,
+}
+
+impl io::Write for Measure {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.width += width(buf);
+
+        if self.width > self.limit || buf.contains(&b'\n') {
+            Err(io::ErrorKind::WriteZero.into())
+        } else {
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// The number of chars in valid utf-8
+fn width(buf: &[u8]) -> usize {
+    buf.iter().filter(|&&b| b & 0xC0 != 0x80).count()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -52,9 +249,45 @@ pub struct PrettyConfig {
     // Whether to emit struct names
     pub struct_names: bool,
     pub separate_tuple_members: bool,
+    // Write the index of each element of a sequence laid out one element
+    // per line as a trailing line comment, ` # [i]`. It used to be written
+    // as `/*[i]*/`, which is not a comment in the grammar and did not read back.
     pub enumerate_arrays: bool,
+    // Floats are always written with a fractional part, so that they read
+    // back as floats. Kept so existing configs still deserialize.
+    #[deprecated(note = "has no effect, floats are always written with a fractional part")]
     pub decimal_floats: bool,
     pub compact_arrays: bool,
+    // Lay out collections on one line when they fit in this many columns
+    pub max_width: Option<usize>,
+    // Which map entries to write in cavetta form, `<key> value`
+    pub cavetta_maps: CavettaMaps,
+    // Sort map keys, normalise floats and leave out trailing commas
+    pub canonical: bool,
+    // Leave out the quotes of strings that read back the same without them
+    pub bare_strings: bool,
+    // Only write `Some(...)` where the content alone would read differently.
+    // Without it every `Some` is written as `Some(...)`. It used to be
+    // written as its bare content, which only reads back with the
+    // `implicit_some` extension on.
+    pub implicit_some: bool,
+    // Write newtype structs as their inner value, and newtype variants as
+    // their name and inner value. The output starts with the pragma that
+    // reads them back.
+    pub unwrap_newtypes: bool,
+    pub unwrap_variant_newtypes: bool,
+}
+
+/// Selects the map entries that `PrettyConfig` writes as `<key> value`
+/// instead of `key: value`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CavettaMaps {
+    #[default]
+    Never,
+    /// Only keys that are not written as a string or an identifier, such as
+    /// numbers and tuples.
+    NonIdentifierKeys,
+    Always,
 }
 
 impl PrettyConfig {
@@ -104,6 +337,8 @@ impl PrettyConfig {
         self
     }
 
+    #[deprecated(note = "has no effect, floats are always written with a fractional part")]
+    #[allow(deprecated)]
     pub fn decimal_floats(mut self, decimal_floats: bool) -> Self {
         self.decimal_floats = decimal_floats;
 
@@ -115,9 +350,52 @@ impl PrettyConfig {
 
         self
     }
+
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+
+        self
+    }
+
+    pub fn cavetta_maps(mut self, cavetta_maps: CavettaMaps) -> Self {
+        self.cavetta_maps = cavetta_maps;
+
+        self
+    }
+
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+
+        self
+    }
+
+    pub fn bare_strings(mut self, bare_strings: bool) -> Self {
+        self.bare_strings = bare_strings;
+
+        self
+    }
+
+    pub fn implicit_some(mut self, implicit_some: bool) -> Self {
+        self.implicit_some = implicit_some;
+
+        self
+    }
+
+    pub fn unwrap_newtypes(mut self, unwrap_newtypes: bool) -> Self {
+        self.unwrap_newtypes = unwrap_newtypes;
+
+        self
+    }
+
+    pub fn unwrap_variant_newtypes(mut self, unwrap_variant_newtypes: bool) -> Self {
+        self.unwrap_variant_newtypes = unwrap_variant_newtypes;
+
+        self
+    }
 }
 
 impl Default for PrettyConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         PrettyConfig {
             depth_limit: !0,
@@ -129,15 +407,42 @@ impl Default for PrettyConfig {
             enumerate_arrays: false,
             decimal_floats: false,
             compact_arrays: false,
+            max_width: None,
+            cavetta_maps: CavettaMaps::Never,
+            canonical: false,
+            bare_strings: false,
+            implicit_some: false,
+            unwrap_newtypes: false,
+            unwrap_variant_newtypes: false,
         }
     }
 }
 
+// The path of the value being written, e.g. `keys.49[2]`, and the length it
+// had before each of its segments.
+#[derive(Default)]
+struct Path {
+    text: String,
+    lengths: Vec<usize>,
+}
+
+// The canonical form of the struct fields of the value to diff against.
+struct Diff {
+    defaults: HashMap<String, String>,
+    recording: bool,
+    // The path depth of a value whose struct fields are diffed, if any
+    armed: Option<usize>,
+}
+
 pub struct Serializer<W: io::Write> {
-    output: W,
+    output: Output<W>,
     pretty: Option<(PrettyConfig, Pretty)>,
     is_empty: Option<bool>,
-    newtype_variant: bool,
+    raw_value: bool,
+    path: Option<Path>,
+    comments: Option<CommentMap>,
+    diff: Option<Diff>,
+    canonical: bool,
 }
 
 impl<W: io::Write> Serializer<W> {
@@ -146,18 +451,54 @@ impl<W: io::Write> Serializer<W> {
     }
 
     pub fn with_options(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
-        Ok(Serializer {
-            output: writer,
+        let canonical = config.as_ref().is_some_and(|config| config.canonical);
+
+        let mut s = Serializer {
+            output: Output::new(writer),
             pretty: config.map(|conf| {(
                 conf,
                 Pretty {
                     indent: 0,
                     sequence_index: Vec::new(),
+                    flat_limit: !0,
                 })
             }),
             is_empty: None,
-            newtype_variant: true,
-        })
+            raw_value: false,
+            path: None,
+            comments: None,
+            diff: None,
+            canonical,
+        };
+
+        s.write_pragma()?;
+
+        Ok(s)
+    }
+
+    /// Writes the pragma enabling the extensions the output is written with,
+    /// so that it reads back as it was written.
+    fn write_pragma(&mut self) -> Result<()> {
+        let (config, _) = match self.pretty {
+            Some(ref pretty) => pretty,
+            None => return Ok(()),
+        };
+
+        let extensions = [
+            (config.unwrap_newtypes, Extension::UnwrapNewtypes),
+            (config.unwrap_variant_newtypes, Extension::UnwrapVariantNewtypes),
+        ];
+        let names = extensions.iter()
+            .filter(|&&(enabled, _)| enabled)
+            .map(|&(_, extension)| extension.name())
+            .collect::<Vec<_>>();
+
+        if !names.is_empty() {
+            let new_line = config.new_line.clone();
+            write!(self.output, "#![enable({})]{}", names.join(", "), new_line)?;
+        }
+
+        Ok(())
     }
 
     fn separate_tuple_members(&self) -> bool {
@@ -166,10 +507,28 @@ impl<W: io::Write> Serializer<W> {
             .map_or(false, |&(ref config, _)| config.separate_tuple_members)
     }
 
-    fn decimal_floats(&self) -> bool {
+    fn bare_strings(&self) -> bool {
         self.pretty
             .as_ref()
-            .map_or(false, |&(ref config, _)| config.decimal_floats)
+            .is_some_and(|(config, _)| config.bare_strings)
+    }
+
+    fn unwrap_newtypes(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.unwrap_newtypes)
+    }
+
+    fn unwrap_variant_newtypes(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.unwrap_variant_newtypes)
+    }
+
+    fn implicit_some(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.implicit_some)
     }
 
     fn compact_arrays(&self) -> bool {
@@ -178,10 +537,186 @@ impl<W: io::Write> Serializer<W> {
             .map_or(false, |&(ref config, _)| config.compact_arrays)
     }
 
+    fn cavetta<T>(&self, key: &T) -> Result<bool>
+    where T: ?Sized + Serialize {
+        match self.pretty {
+            Some((ref config, _)) => match config.cavetta_maps {
+                CavettaMaps::Never => Ok(false),
+                CavettaMaps::NonIdentifierKeys => is_identifier_key(key).map(|is| !is),
+                CavettaMaps::Always => Ok(true),
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn push_key(&mut self, key: &str) {
+        if let Some(ref mut path) = self.path {
+            path.lengths.push(path.text.len());
+
+            if path.lengths.len() > 1 {
+                path.text.push('.');
+            }
+
+            path.text.push_str(key);
+        }
+    }
+
+    fn push_index(&mut self, index: usize) {
+        if let Some(ref mut path) = self.path {
+            path.lengths.push(path.text.len());
+            path.text.push_str(&format!("[{}]", index));
+        }
+    }
+
+    fn pop_path(&mut self) {
+        if let Some(ref mut path) = self.path {
+            if let Some(length) = path.lengths.pop() {
+                path.text.truncate(length);
+            }
+        }
+    }
+
+    /// Lets the struct about to be written, if it comes next, diff its fields.
+    fn arm_diff(&mut self) {
+        if let (Some(ref mut diff), Some(ref path)) = (&mut self.diff, &self.path) {
+            diff.armed = Some(path.lengths.len());
+        }
+    }
+
+    /// Whether the struct being written diffs its fields, which it only does
+    /// when it is the value the diff was armed for.
+    fn take_diff(&mut self) -> bool {
+        let depth = self.path.as_ref().map(|path| path.lengths.len());
+
+        match self.diff {
+            Some(ref mut diff) => diff.armed.take().is_some_and(|armed| Some(armed) == depth),
+            None => false,
+        }
+    }
+
+    /// Records or compares a field of a diffed struct, returning whether it
+    /// equals the field of the defaults and can be left out.
+    fn diff_field<T>(&mut self, value: &T) -> Result<bool>
+    where T: ?Sized + Serialize {
+        let (diff, path) = match (&mut self.diff, &self.path) {
+            (Some(diff), Some(path)) => (diff, path),
+            _ => return Ok(false),
+        };
+
+        let canonical = to_string_canonical(value)?;
+
+        if diff.recording {
+            diff.defaults.insert(path.text.clone(), canonical);
+            Ok(false)
+        } else {
+            Ok(diff.defaults.get(&path.text) == Some(&canonical))
+        }
+    }
+
+    /// Writes the comment of the current path as `#` lines at the current
+    /// indent. A comment only fits when the value starts its own line.
+    fn write_comment(&mut self, own_line: bool) -> Result<()> {
+        let comment = match (&self.comments, &self.path) {
+            (Some(comments), Some(path)) if own_line => comments.get(&path.text),
+            _ => None,
+        };
+
+        if let (Some(comment), Some((ref config, ref pretty))) = (comment, &self.pretty) {
+            for line in comment.lines() {
+                for _ in 0..pretty.indent {
+                    self.output.write_all(config.indentor.as_bytes())?;
+                }
+
+                if line.is_empty() {
+                    self.output.write_all(b"#")?;
+                } else {
+                    write!(self.output, "# {}", line)?;
+                }
+
+                self.output.write_all(config.new_line.as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes a value on one line if it fits in `max_width`, along with
+    /// the `trailing` columns that follow it. Otherwise its collections break
+    /// over several lines, each deciding again for its own entries.
+    fn serialize_fitted<T>(&mut self, value: &T, trailing: usize) -> Result<()>
+    where T: ?Sized + Serialize {
+        if !self.fits(value, trailing) {
+            return value.serialize(&mut *self);
+        }
+
+        let flat_limit = match self.pretty {
+            Some((_, ref mut pretty)) => std::mem::replace(&mut pretty.flat_limit, pretty.indent),
+            None => !0,
+        };
+
+        let res = value.serialize(&mut *self);
+
+        if let Some((_, ref mut pretty)) = self.pretty {
+            pretty.flat_limit = flat_limit;
+        }
+
+        res
+    }
+
+    fn fits<T>(&self, value: &T, trailing: usize) -> bool
+    where T: ?Sized + Serialize {
+        let (config, pretty) = match self.pretty {
+            Some((ref config, ref pretty)) => (config, pretty),
+            None => return false,
+        };
+
+        // Already on one line
+        if pretty.indent >= config.depth_limit.min(pretty.flat_limit) {
+            return false;
+        }
+
+        // Comments need the entries they belong to to start their own lines
+        if let (Some(comments), Some(path)) = (&self.comments, &self.path) {
+            let path = path.text.as_str();
+
+            let inside = comments.iter().any(|(commented, _)| {
+                commented.strip_prefix(path).is_some_and(|rest| {
+                    !rest.is_empty() && (path.is_empty() || rest.starts_with(['.', '[']))
+                })
+            });
+
+            if inside {
+                return false;
+            }
+        }
+
+        let limit = match config.max_width {
+            Some(max_width) => max_width.saturating_sub(self.output.column + trailing),
+            None => return false,
+        };
+
+        let mut probe = Serializer {
+            output: Output::new(Measure { width: 0, limit }),
+            pretty: Some((config.clone(), Pretty {
+                indent: pretty.indent,
+                sequence_index: Vec::new(),
+                flat_limit: pretty.indent,
+            })),
+            is_empty: None,
+            raw_value: false,
+            path: None,
+            comments: None,
+            diff: None,
+            canonical: self.canonical,
+        };
+
+        value.serialize(&mut probe).is_ok()
+    }
+
     fn start_indent(&mut self) -> Result<()> {
         if let Some((ref config, ref mut pretty)) = self.pretty {
             pretty.indent += 1;
-            if pretty.indent <= config.depth_limit {
+            if pretty.breaks(config) {
                 let is_empty = self.is_empty.unwrap_or(false);
 
                 if !is_empty {
@@ -194,7 +729,7 @@ impl<W: io::Write> Serializer<W> {
 
     fn indent(&mut self) -> io::Result<()> {
         if let Some((ref config, ref pretty)) = self.pretty {
-            if pretty.indent <= config.depth_limit {
+            if pretty.breaks(config) {
                 for _ in 0..pretty.indent {
                     self.output.write_all(config.indentor.as_bytes())?;
                 }
@@ -205,7 +740,7 @@ impl<W: io::Write> Serializer<W> {
 
     fn end_indent(&mut self) -> io::Result<()> {
         if let Some((ref config, ref mut pretty)) = self.pretty {
-            if pretty.indent <= config.depth_limit {
+            if pretty.breaks(config) {
                 let is_empty = self.is_empty.unwrap_or(false);
 
                 if !is_empty {
@@ -232,6 +767,17 @@ impl<W: io::Write> Serializer<W> {
         Ok(())
     }
 
+    /// Writes a float so that it reads back as a float, never as an integer.
+    fn serialize_float(&mut self, repr: String, is_finite: bool) -> Result<()> {
+        self.output.write_all(repr.as_bytes())?;
+
+        if is_finite && !repr.contains('.') {
+            self.output.write_all(b".0")?;
+        }
+
+        Ok(())
+    }
+
     fn serialize_sint(&mut self, value: impl Into<LargeSInt>) -> Result<()> {
         // TODO optimize
         write!(self.output, "{}", value.into())?;
@@ -246,6 +792,13 @@ impl<W: io::Write> Serializer<W> {
         Ok(())
     }
 
+    /// Whether the entries of the current map or struct each start a line.
+    fn own_line(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, pretty)| pretty.breaks(config))
+    }
+
     fn write_identifier(&mut self, name: &str) -> io::Result<()> {
         let mut bytes = name.as_bytes().iter().cloned();
         if !bytes.next().map_or(false, is_ident_first_char) || !bytes.all(is_ident_other_char) {
@@ -263,6 +816,37 @@ impl<W: io::Write> Serializer<W> {
     }
 }
 
+/// Whether a map key is written as a string or a bare identifier, which reads
+/// well as `key: value`.
+fn is_identifier_key<T>(key: &T) -> Result<bool>
+where T: ?Sized + Serialize {
+    let written = to_compact_bytes(key)?;
+    let is_string = written.first() == Some(&b'"');
+    let is_identifier = written.first().is_some_and(|&b| is_ident_first_char(b))
+        && written.iter().all(|&b| is_ident_other_char(b));
+
+    Ok(is_string || is_identifier)
+}
+
+/// Whether the content of an option, written alone, would read as something
+/// else where an option is expected: `None`, or the explicit `Some(` of an
+/// option within.
+fn needs_some(content: &[u8]) -> bool {
+    let starts_with_ident = |ident: &[u8]| {
+        content.starts_with(ident) && !content.get(ident.len()).is_some_and(|&b| is_ident_other_char(b))
+    };
+
+    starts_with_ident(b"None")
+        || starts_with_ident(b"Some") && content[4..].iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'(')
+}
+
+fn to_compact_bytes<T>(value: &T) -> Result<Vec<u8>>
+where T: ?Sized + Serialize {
+    let mut output = Vec::new();
+    value.serialize(&mut Serializer::with_options(&mut output, None)?)?;
+    Ok(output)
+}
+
 impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     type Error = Error;
     type Ok = ();
@@ -312,19 +896,14 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
-        write!(self.output, "{}", v)?;
-        if self.decimal_floats() && (v - v.floor()).abs() < f32::EPSILON {
-            write!(self.output, ".0")?;
-        }
-        Ok(())
+        // `-0.0 == 0.0`, so the canonical form has a single zero
+        let v = if self.canonical && v == 0.0 { 0.0 } else { v };
+        self.serialize_float(v.to_string(), v.is_finite())
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        write!(self.output, "{}", v)?;
-        if self.decimal_floats() && (v - v.floor()).abs() < f64::EPSILON {
-            write!(self.output, ".0")?;
-        }
-        Ok(())
+        let v = if self.canonical && v == 0.0 { 0.0 } else { v };
+        self.serialize_float(v.to_string(), v.is_finite())
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
@@ -338,7 +917,18 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.serialize_escaped_str(v)?;
+        if self.raw_value {
+            self.raw_value = false;
+            self.output.write_all(v.as_bytes())?;
+
+            return Ok(());
+        }
+
+        if self.bare_strings() && is_bare_string(v) {
+            self.output.write_all(v.as_bytes())?;
+        } else {
+            self.serialize_escaped_str(v)?;
+        }
 
         Ok(())
     }
@@ -355,22 +945,44 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        value.serialize(&mut *self)?;
+        // An option is replaced whole when read, so its content is not diffed
+        self.take_diff();
+
+        if !self.implicit_some() {
+            self.output.write_all(b"Some(")?;
+            value.serialize(&mut *self)?;
+            self.output.write_all(b")")?;
+
+            return Ok(());
+        }
+
+        let column = self.output.column;
+        self.output.captures.push(Vec::new());
+        let res = value.serialize(&mut *self);
+        let content = self.output.captures.pop().unwrap_or_default();
+        res?;
+
+        self.output.column = column;
+
+        if needs_some(&content) {
+            self.output.write_all(b"Some(")?;
+            self.output.write_all(&content)?;
+            self.output.write_all(b")")?;
+        } else {
+            self.output.write_all(&content)?;
+        }
+
         Ok(())
     }
 
     fn serialize_unit(self) -> Result<()> {
-        if !self.newtype_variant {
-            self.output.write_all(b"()")?;
-        }
-
-        self.newtype_variant = false;
+        self.output.write_all(b"()")?;
 
         Ok(())
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
-        if self.struct_names() && !self.newtype_variant {
+        if self.struct_names() {
             self.write_identifier(name)?;
 
             Ok(())
@@ -387,6 +999,19 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        if name == crate::value::RAW_VALUE_TOKEN {
+            // `RawValue` serializes its source as a str, which is written verbatim
+            self.raw_value = true;
+            let res = value.serialize(&mut *self);
+            self.raw_value = false;
+
+            return res;
+        }
+
+        if self.unwrap_newtypes() {
+            return value.serialize(&mut *self);
+        }
+
         if self.struct_names() {
             self.write_identifier(name)?;
         }
@@ -399,22 +1024,22 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     fn serialize_newtype_variant<T>(self, _: &'static str, _: u32, variant: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        self.take_diff();
+
         self.write_identifier(variant)?;
-        self.output.write_all(b"(")?;
 
-        self.newtype_variant = true;
+        if self.unwrap_variant_newtypes() {
+            self.output.write_all(b" ")?;
+            return value.serialize(&mut *self);
+        }
 
+        self.output.write_all(b"(")?;
         value.serialize(&mut *self)?;
-
-        self.newtype_variant = false;
-
         self.output.write_all(b")")?;
         Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.newtype_variant = false;
-
         self.output.write_all(b"[")?;
 
         if let Some(len) = len {
@@ -432,17 +1057,15 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: false,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff: false,
         })
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        let old_newtype_variant = self.newtype_variant;
-        self.newtype_variant = false;
-
-        if !old_newtype_variant {
-            self.output.write_all(b"(")?;
-        }
+        self.output.write_all(b"(")?;
 
         if self.separate_tuple_members() {
             self.is_empty = Some(len == 0);
@@ -453,12 +1076,15 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: old_newtype_variant,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff: false,
         })
     }
 
     fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
-        if self.struct_names() && !self.newtype_variant {
+        if self.struct_names() {
             self.write_identifier(name)?;
         }
 
@@ -466,8 +1092,6 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant> {
-        self.newtype_variant = false;
-
         self.write_identifier(variant)?;
         self.output.write_all(b"(")?;
 
@@ -480,13 +1104,14 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: false,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff: false,
         })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.newtype_variant = false;
-
         self.output.write_all(b"{")?;
 
         if let Some(len) = len {
@@ -498,20 +1123,20 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: false,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff: false,
         })
     }
 
     fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        let old_newtype_variant = self.newtype_variant;
-        self.newtype_variant = false;
+        let diff = self.take_diff();
 
-        if !old_newtype_variant {
-            if self.struct_names() {
-                self.write_identifier(name)?;
-            }
-            self.output.write_all(b"(")?;
+        if self.struct_names() {
+            self.write_identifier(name)?;
         }
+        self.output.write_all(b"{")?;
 
         self.is_empty = Some(len == 0);
         self.start_indent()?;
@@ -519,15 +1144,16 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: old_newtype_variant,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff,
         })
     }
 
     fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant> {
-        self.newtype_variant = false;
-
         self.write_identifier(variant)?;
-        self.output.write_all(b"(")?;
+        self.output.write_all(b"{")?;
 
         self.is_empty = Some(len == 0);
         self.start_indent()?;
@@ -535,7 +1161,10 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(Compound {
             ser: self,
             state: State::First,
-            newtype_variant: false,
+            cavetta: false,
+            index: 0,
+            entries: Vec::new(),
+            diff: false,
         })
     }
 }
@@ -549,7 +1178,60 @@ enum State {
 pub struct Compound<'a, W: io::Write> {
     ser: &'a mut Serializer<W>,
     state: State,
-    newtype_variant: bool,
+    // Whether the map key just written opened with `<`
+    cavetta: bool,
+    // The index of the next element of a sequence or tuple
+    index: usize,
+    // Map entries held back to be written in order, keyed by how they sort
+    entries: Vec<(Value, Vec<u8>)>,
+    // Whether struct fields equal to those of the diffed defaults are left out
+    diff: bool,
+}
+
+impl<'a, W: io::Write> Compound<'a, W> {
+    /// Writes the comma before an entry of a map or struct, and the line
+    /// break or separator after it.
+    fn separate_entry(&mut self) -> Result<()> {
+        self.ser.output.write_all(b",")?;
+
+        if let Some((ref config, ref pretty)) = self.ser.pretty {
+            if pretty.breaks(config) {
+                self.ser.output.write_all(config.new_line.as_bytes())?;
+            } else {
+                self.ser.output.write_all(config.separator.as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the last comma of a collection, unless canonical, and the line
+    /// break before its end.
+    fn end_entries(&mut self) -> Result<()> {
+        if !self.ser.canonical {
+            self.ser.output.write_all(b",")?;
+        }
+
+        if let Some((ref config, _)) = self.ser.pretty {
+            self.ser.output.write_all(config.new_line.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the index of the element before the comma just written as a
+    /// line comment, when the sequence is laid out one element per line.
+    fn enumerate_previous(&mut self) -> Result<()> {
+        if let Some((ref config, ref pretty)) = self.ser.pretty {
+            if pretty.breaks(config) && !config.compact_arrays && config.enumerate_arrays {
+                if let Some(index) = pretty.sequence_index.last() {
+                    write!(self.ser.output, " # [{}]", index - 1)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
@@ -562,8 +1244,10 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
             self.state = State::Rest;
         } else {
             self.ser.output.write_all(b",")?;
+            self.enumerate_previous()?;
+
             if let Some((ref config, ref mut pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit && !config.compact_arrays {
+                if pretty.breaks(config) && !config.compact_arrays {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
                     self.ser.output.write_all(config.separator.as_bytes())?;
@@ -571,28 +1255,44 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
             }
         }
 
+        let own_line = self.ser.pretty
+            .as_ref()
+            .is_some_and(|(config, pretty)| pretty.breaks(config) && !config.compact_arrays);
+
+        self.ser.push_index(self.index);
+        self.index += 1;
+        self.ser.write_comment(own_line)?;
+
         if !self.ser.compact_arrays() {
             self.ser.indent()?;
         }
 
-        if let Some((ref mut config, ref mut pretty)) = self.ser.pretty {
-            if pretty.indent <= config.depth_limit && config.enumerate_arrays {
-                let index = pretty.sequence_index.last_mut().unwrap();
-                write!(self.ser.output, "/*[{}]*/ ", index)?;
+        if let Some((_, ref mut pretty)) = self.ser.pretty {
+            if let Some(index) = pretty.sequence_index.last_mut() {
                 *index += 1;
             }
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_fitted(value, 1)?;
+        self.ser.pop_path();
 
         Ok(())
     }
 
-    fn end(self) -> Result<()> {
+    fn end(mut self) -> Result<()> {
         if let State::Rest = self.state {
-            if let Some((ref config, ref mut pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit && !config.compact_arrays {
+            let one_per_line = self.ser.pretty
+                .as_ref()
+                .is_some_and(|(config, pretty)| pretty.breaks(config) && !config.compact_arrays);
+
+            if one_per_line {
+                if !self.ser.canonical {
                     self.ser.output.write_all(b",")?;
+                }
+
+                self.enumerate_previous()?;
+
+                if let Some((ref config, _)) = self.ser.pretty {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 }
             }
@@ -606,7 +1306,6 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
             pretty.sequence_index.pop();
         }
 
-        // seq always disables `self.newtype_variant`
         self.ser.output.write_all(b"]")?;
         Ok(())
     }
@@ -623,7 +1322,7 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
         } else {
             self.ser.output.write_all(b",")?;
             if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit && self.ser.separate_tuple_members() {
+                if pretty.breaks(config) && self.ser.separate_tuple_members() {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
                     self.ser.output.write_all(config.separator.as_bytes())?;
@@ -631,31 +1330,35 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
             }
         }
 
+        let own_line = self.ser.separate_tuple_members() && self.ser.pretty
+            .as_ref()
+            .is_some_and(|(config, pretty)| pretty.breaks(config));
+
+        self.ser.push_index(self.index);
+        self.index += 1;
+        self.ser.write_comment(own_line)?;
+
         if self.ser.separate_tuple_members() {
             self.ser.indent()?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_fitted(value, 1)?;
+        self.ser.pop_path();
 
         Ok(())
     }
 
-    fn end(self) -> Result<()> {
+    fn end(mut self) -> Result<()> {
         if let State::Rest = self.state {
-            if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if self.ser.separate_tuple_members() && pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
-                }
+            if self.ser.separate_tuple_members() && self.ser.own_line() {
+                self.end_entries()?;
             }
         }
         if self.ser.separate_tuple_members() {
             self.ser.end_indent()?;
         }
 
-        if !self.newtype_variant {
-            self.ser.output.write_all(b")")?;
-        }
+        self.ser.output.write_all(b")")?;
 
         Ok(())
     }
@@ -696,47 +1399,77 @@ impl<'a, W: io::Write> ser::SerializeMap for Compound<'a, W> {
 
     fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        if let State::First = self.state {
+        if self.ser.canonical {
+            // Entries are separated once they are sorted, in `end`
             self.state = State::Rest;
-        } else {
-            self.ser.output.write_all(b",")?;
+            self.entries.push((to_value(key)?, Vec::new()));
+            self.ser.output.captures.push(Vec::new());
 
-            if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
-                } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
-                }
+            if self.ser.own_line() {
+                self.ser.output.column = 0;
             }
+        } else if let State::First = self.state {
+            self.state = State::Rest;
+        } else {
+            self.separate_entry()?;
+        }
+
+        if self.ser.path.is_some() {
+            let key = to_compact_bytes(key)?;
+            self.ser.push_key(&String::from_utf8_lossy(unquoted(&key)));
+            self.ser.write_comment(self.ser.own_line())?;
         }
+
         self.ser.indent()?;
+
+        self.cavetta = self.ser.cavetta(key)?;
+        if self.cavetta {
+            self.ser.output.write_all(b"<")?;
+        }
+
         key.serialize(&mut *self.ser)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        self.ser.output.write_all(b":")?;
+        self.ser.output.write_all(if self.cavetta { b">" } else { b":" })?;
 
         if let Some((ref config, _)) = self.ser.pretty {
             self.ser.output.write_all(config.separator.as_bytes())?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_fitted(value, 1)?;
+        self.ser.pop_path();
+
+        if self.ser.canonical {
+            if let (Some(entry), Some(captured)) = (self.entries.last_mut(), self.ser.output.captures.pop()) {
+                entry.1 = captured;
+            }
+        }
 
         Ok(())
     }
 
-    fn end(self) -> Result<()> {
-        if let State::Rest = self.state {
-            if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
+    fn end(mut self) -> Result<()> {
+        if self.ser.canonical {
+            let mut entries = std::mem::take(&mut self.entries);
+            entries.sort();
+
+            for (i, (_, entry)) in entries.iter().enumerate() {
+                if i > 0 {
+                    self.separate_entry()?;
                 }
+
+                self.ser.output.write_all(entry)?;
+            }
+        }
+
+        if let State::Rest = self.state {
+            if self.ser.own_line() {
+                self.end_entries()?;
             }
         }
         self.ser.end_indent()?;
-        // map always disables `self.newtype_variant`
         self.ser.output.write_all(b"}")?;
         Ok(())
     }
@@ -748,19 +1481,20 @@ impl<'a, W: io::Write> ser::SerializeStruct for Compound<'a, W> {
 
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        self.ser.push_key(key);
+
+        if self.diff && self.ser.diff_field(value)? {
+            self.ser.pop_path();
+            return Ok(());
+        }
+
         if let State::First = self.state {
             self.state = State::Rest;
         } else {
-            self.ser.output.write_all(b",")?;
-
-            if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
-                } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
-                }
-            }
+            self.separate_entry()?;
         }
+        self.ser.write_comment(self.ser.own_line())?;
+
         self.ser.indent()?;
         self.ser.write_identifier(key)?;
         self.ser.output.write_all(b":")?;
@@ -769,24 +1503,24 @@ impl<'a, W: io::Write> ser::SerializeStruct for Compound<'a, W> {
             self.ser.output.write_all(config.separator.as_bytes())?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        if self.diff {
+            self.ser.arm_diff();
+        }
+
+        self.ser.serialize_fitted(value, 1)?;
+        self.ser.pop_path();
 
         Ok(())
     }
 
-    fn end(self) -> Result<()> {
+    fn end(mut self) -> Result<()> {
         if let State::Rest = self.state {
-            if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
-                }
+            if self.ser.own_line() {
+                self.end_entries()?;
             }
         }
         self.ser.end_indent()?;
-        if !self.newtype_variant {
-            self.ser.output.write_all(b")")?;
-        }
+        self.ser.output.write_all(b"}")?;
         Ok(())
     }
 }
@@ -804,3 +1538,6 @@ impl<'a, W: io::Write> ser::SerializeStructVariant for Compound<'a, W> {
         ser::SerializeStruct::end(self)
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,768 @@
+use super::*;
+
+use crate::comment_map::CommentMap;
+use crate::de::{ from_str, from_str_with_source_map };
+use crate::value::{ Map, Number, Value };
+use std::collections::{ BTreeMap, HashMap };
+use std::fmt::Debug;
+use serde::de::DeserializeOwned;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct UnitStruct;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Newtype(i32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct TupleStruct(u8, String);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Empty {}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Named {
+    a: bool,
+    b: Option<char>,
+    c: Vec<f32>,
+    #[serde(rename = "kind-of.thing+")]
+    kind: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Enum {
+    Unit,
+    Newtype(Named),
+    NewtypeUnit(()),
+    Tuple(i16, f64),
+    Struct { x: u64, y: Option<Box<Enum>> },
+    EmptyStruct {},
+    #[serde(rename = "2d")]
+    Raw,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Everything {
+    unit: (),
+    unit_struct: UnitStruct,
+    newtype: Newtype,
+    tuple: (i8, u16, String),
+    empty_tuple: [u8; 0],
+    tuple_struct: TupleStruct,
+    empty: Empty,
+    named: Named,
+    enums: Vec<Enum>,
+    map: BTreeMap<String, Vec<i64>>,
+    keyed: BTreeMap<(bool, char), Option<Newtype>>,
+    #[serde(with = "serde_bytes")]
+    bytes: Vec<u8>,
+    optional_unit: Option<()>,
+    optional_struct: Option<UnitStruct>,
+    nested: Option<Vec<Option<i32>>>,
+    options: Vec<Option<Option<Option<UnitStruct>>>>,
+    strings: Vec<String>,
+    integers: (i8, i16, i32, i64, u8, u16, u32, u64),
+    floats: (f32, f64),
+    value: Value,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Font {
+    family: String,
+    size: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Appearance {
+    font: Font,
+    theme: Option<Enum>,
+    colors: BTreeMap<String, (u8, u8, u8)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    volume: u8,
+    appearance: Appearance,
+    recent: Vec<Font>,
+    window: Option<Font>,
+}
+
+// A small xorshift generator, so the random cases are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn bool(&mut self) -> bool {
+        self.below(2) == 0
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize].clone()
+    }
+
+    fn char(&mut self) -> char {
+        self.pick(&['a', 'Z', '0', ' ', '\'', '"', '\\', '\n', '\t', '\0', '#', '{', ')', ':', ',', 'わ', '朦', '\u{7f}', '\u{301}', '🦀'])
+    }
+
+    fn string(&mut self) -> String {
+        (0..self.below(8)).map(|_| self.char()).collect()
+    }
+
+    fn i64(&mut self) -> i64 {
+        match self.below(4) {
+            0 => self.pick(&[0, 1, -1, i64::MIN, i64::MAX, i8::MIN as i64, u8::MAX as i64, i32::MIN as i64]),
+            1 => self.below(256) as i64 - 128,
+            _ => self.next() as i64,
+        }
+    }
+
+    fn f64(&mut self) -> f64 {
+        match self.below(3) {
+            0 => self.pick(&[
+                0.0, -0.0, 1.0, -2.0, 0.1, 1.5, 1.0 / 3.0, 1e21, 1e-7, 123456789.0,
+                f64::MAX, f64::MIN, f64::MIN_POSITIVE, f64::EPSILON,
+                f64::INFINITY, f64::NEG_INFINITY, f64::NAN,
+            ]),
+            1 => (self.i64() % 100_000) as f64 / 64.0,
+            _ => f64::from_bits(self.next()),
+        }
+    }
+
+    fn f32(&mut self) -> f32 {
+        match self.below(2) {
+            0 => self.f64() as f32,
+            _ => f32::from_bits(self.next() as u32),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Value {
+        let kinds = if depth == 0 { 6 } else { 9 };
+
+        match self.below(kinds) {
+            0 => Value::Bool(self.bool()),
+            1 => Value::Char(self.char()),
+            2 => Value::Number(Number::new(self.i64())),
+            3 => Value::Number(Number::new(self.f64())),
+            4 => Value::String(self.string()),
+            5 => Value::Unit,
+            6 => Value::Option(match self.bool() {
+                true => Some(Box::new(self.value(depth - 1))),
+                false => None,
+            }),
+            7 => Value::Seq((0..self.below(5)).map(|_| self.value(depth - 1)).collect()),
+            _ => Value::Map(
+                (0..self.below(5))
+                    .map(|_| (self.value(depth - 1), self.value(depth - 1)))
+                    .collect::<Map>(),
+            ),
+        }
+    }
+
+    fn named(&mut self) -> Named {
+        Named {
+            a: self.bool(),
+            b: if self.bool() { Some(self.char()) } else { None },
+            c: (0..self.below(4)).map(|_| self.f32()).filter(|f| !f.is_nan()).collect(),
+            kind: self.string(),
+        }
+    }
+
+    fn enumeration(&mut self, depth: usize) -> Enum {
+        match self.below(if depth == 0 { 6 } else { 7 }) {
+            0 => Enum::Unit,
+            1 => Enum::Newtype(self.named()),
+            2 => Enum::NewtypeUnit(()),
+            3 => Enum::Tuple(self.i64() as i16, self.f64()),
+            4 => Enum::EmptyStruct {},
+            5 => Enum::Raw,
+            _ => Enum::Struct {
+                x: self.next(),
+                y: if self.bool() { Some(Box::new(self.enumeration(depth - 1))) } else { None },
+            },
+        }
+    }
+
+    fn everything(&mut self) -> Everything {
+        Everything {
+            unit: (),
+            unit_struct: UnitStruct,
+            newtype: Newtype(self.i64() as i32),
+            tuple: (self.i64() as i8, self.i64() as u16, self.string()),
+            empty_tuple: [],
+            tuple_struct: TupleStruct(self.i64() as u8, self.string()),
+            empty: Empty {},
+            named: self.named(),
+            enums: (0..self.below(4)).map(|_| self.enumeration(2)).collect(),
+            map: (0..self.below(4)).map(|_| (self.string(), vec![self.i64(); self.below(3) as usize])).collect(),
+            keyed: (0..self.below(4))
+                .map(|_| ((self.bool(), self.char()), if self.bool() { Some(Newtype(self.i64() as i32)) } else { None }))
+                .collect(),
+            bytes: (0..self.below(6)).map(|_| self.next() as u8).collect(),
+            optional_unit: if self.bool() { Some(()) } else { None },
+            optional_struct: if self.bool() { Some(UnitStruct) } else { None },
+            nested: if self.bool() { Some(vec![Some(self.i64() as i32), None]) } else { None },
+            options: vec![None, Some(None), Some(Some(None)), Some(Some(Some(UnitStruct)))],
+            strings: (0..self.below(4))
+                .map(|_| self.pick(&["None", "Some", "Some(", "none", "rald", "-1", "true", "a b", "#x", "わ", ""]).to_owned() + &self.string())
+                .collect(),
+            integers: (
+                self.i64() as i8, self.i64() as i16, self.i64() as i32, self.i64(),
+                self.i64() as u8, self.i64() as u16, self.i64() as u32, self.next(),
+            ),
+            floats: (self.f32(), self.f64()),
+            value: self.value(2),
+        }
+    }
+
+    fn settings(&mut self) -> Settings {
+        let mut settings = Settings::default();
+
+        if self.bool() {
+            settings.volume = self.next() as u8;
+        }
+        if self.bool() {
+            settings.appearance.font.family = self.string();
+        }
+        if self.bool() {
+            settings.appearance.font.size = self.f32();
+        }
+        if self.bool() {
+            settings.appearance.theme = Some(self.enumeration(1));
+        }
+        if self.bool() {
+            settings.appearance.colors.insert(self.string(), (1, 2, 3));
+        }
+        if self.bool() {
+            settings.recent.push(Font { family: self.string(), size: 0.0 });
+        }
+        if self.bool() {
+            settings.window = Some(Font::default());
+        }
+
+        settings
+    }
+}
+
+// `NaN != NaN`, so floats are compared through their formatting instead.
+fn same<T: Debug>(a: &T, b: &T) -> bool {
+    format!("{:?}", a) == format!("{:?}", b)
+}
+
+fn configs() -> Vec<Option<PrettyConfig>> {
+    vec![
+        None,
+        Some(PrettyConfig::new()),
+        Some(PrettyConfig::new().struct_names(true)),
+        Some(PrettyConfig::new().separate_tuple_members(true)),
+        Some(PrettyConfig::new().enumerate_arrays(true)),
+        Some(PrettyConfig::new().compact_arrays(true)),
+        Some(PrettyConfig::new().enumerate_arrays(true).compact_arrays(true)),
+        Some(PrettyConfig::new().depth_limit(0)),
+        Some(PrettyConfig::new().depth_limit(2).enumerate_arrays(true).separate_tuple_members(true)),
+        Some(PrettyConfig::new().new_line(String::from("\r\n")).indentor(String::from("\t"))),
+        Some(PrettyConfig::new().separator(String::new()).indentor(String::new())),
+        Some(PrettyConfig::new().cavetta_maps(CavettaMaps::Always)),
+        Some(PrettyConfig::new().max_width(0)),
+        Some(PrettyConfig::new().canonical(true)),
+        Some(PrettyConfig::new().canonical(true).max_width(40).enumerate_arrays(true).separate_tuple_members(true)),
+        Some(PrettyConfig::new().max_width(24).separate_tuple_members(true)),
+        Some(PrettyConfig::new().max_width(80).enumerate_arrays(true).cavetta_maps(CavettaMaps::NonIdentifierKeys)),
+        Some(PrettyConfig::new().cavetta_maps(CavettaMaps::NonIdentifierKeys)),
+        Some(PrettyConfig::new().cavetta_maps(CavettaMaps::Always).depth_limit(1).separator(String::new())),
+        Some(
+            PrettyConfig::new()
+                .struct_names(true)
+                .separate_tuple_members(true)
+                .enumerate_arrays(true),
+        ),
+    ]
+}
+
+fn bare_strings_configs() -> Vec<Option<PrettyConfig>> {
+    configs().into_iter().flatten().map(|config| Some(config.bare_strings(true))).collect()
+}
+
+// `Value` has no type to tell it an option from its content, so these configs
+// are for typed values only.
+fn implicit_some_configs() -> Vec<Option<PrettyConfig>> {
+    configs()
+        .into_iter()
+        .flatten()
+        .flat_map(|config| vec![
+            config.clone().implicit_some(true),
+            config.bare_strings(true).implicit_some(true),
+        ])
+        .map(Some)
+        .collect()
+}
+
+fn unwrap_newtypes_configs() -> Vec<Option<PrettyConfig>> {
+    configs()
+        .into_iter()
+        .flatten()
+        .flat_map(|config| vec![
+            config.clone().unwrap_newtypes(true),
+            config.clone().unwrap_variant_newtypes(true),
+            config.unwrap_newtypes(true).unwrap_variant_newtypes(true),
+        ])
+        .map(Some)
+        .collect()
+}
+
+fn check_round_trip<T>(value: &T)
+where T: Debug + Serialize + DeserializeOwned {
+    check_round_trip_with(value, configs())
+}
+
+fn check_round_trip_with<T>(value: &T, configs: Vec<Option<PrettyConfig>>)
+where T: Debug + Serialize + DeserializeOwned {
+    // The canonical form reads back as a value with the same canonical form
+    let canonical = to_string_canonical(value).unwrap();
+    let again = from_str::<T>(&canonical).map(|deserialized| to_string_canonical(&deserialized).unwrap());
+    assert_eq!(Ok(&canonical), again.as_ref(), "canonical round trip failed for {:?}", value);
+
+    for config in configs {
+        check_written(value, config);
+    }
+}
+
+// Writes `value` with `config`, checks that the output reads back as `value`
+// and returns it.
+fn check_written<T>(value: &T, config: Option<PrettyConfig>) -> String
+where T: Debug + Serialize + DeserializeOwned {
+    let serialized = match config.clone() {
+        Some(config) => to_string_pretty(value, config),
+        None => to_string(value),
+    }.unwrap();
+
+    // Canonical output writes `-0.0` as `0.0`
+    let normalised = config.as_ref().is_some_and(|config| config.canonical);
+    check_reads_back(value, &serialized, normalised, &config);
+
+    serialized
+}
+
+// Where `normalised`, only the canonical forms of `value` and what is read
+// back have to match.
+fn check_reads_back<T>(value: &T, serialized: &str, normalised: bool, config: &Option<PrettyConfig>)
+where T: Debug + Serialize + DeserializeOwned {
+    match from_str::<T>(serialized) {
+        Ok(ref deserialized) if same(value, deserialized) => (),
+        Ok(ref deserialized) if normalised && to_string_canonical(deserialized).ok() == to_string_canonical(value).ok() => (),
+        other => panic!(
+            "round trip failed with {:?}\n  value: {:?}\n  serialized: {}\n  deserialized: {:?}",
+            config, value, serialized, other
+        ),
+    }
+}
+
+#[test]
+fn round_trip_values() {
+    let mut rng = Rng(0x5eed_0000_0000_0001);
+
+    for _ in 0..500 {
+        check_round_trip(&rng.value(4));
+    }
+}
+
+#[test]
+fn round_trip_derived() {
+    let mut rng = Rng(0x5eed_0000_0000_0002);
+
+    for _ in 0..200 {
+        check_round_trip(&rng.everything());
+    }
+}
+
+#[test]
+fn round_trip_edge_cases() {
+    check_round_trip(&Value::Map(Map::new()));
+    check_round_trip(&Value::Seq(vec![]));
+    check_round_trip(&Value::Option(Some(Box::new(Value::Unit))));
+    check_round_trip(&Value::Option(Some(Box::new(Value::Option(None)))));
+    check_round_trip(&vec![Some(Enum::Unit), None]);
+    check_round_trip(&(Enum::Raw, UnitStruct, Newtype(-1), (), [(); 0]));
+    check_round_trip(&String::from("None"));
+    check_round_trip(&'\'');
+    check_round_trip(&1.0f32);
+    check_round_trip(&-0.0f64);
+}
+
+#[test]
+fn test_struct() {
+    #[derive(Serialize)]
+    struct MyStruct {
+        x: f32,
+        y: f32,
+    }
+
+    let my_struct = MyStruct { x: 4.0, y: 7.5 };
+    assert_eq!(Ok(String::from("{x:4.0,y:7.5}")), to_string(&my_struct));
+    assert_eq!(
+        Ok(String::from("MyStruct{\n    x: 4.0,\n    y: 7.5,\n}")),
+        to_string_pretty(&my_struct, PrettyConfig::new().struct_names(true))
+    );
+}
+
+#[test]
+fn test_enumerate_arrays() {
+    assert_eq!(
+        Ok(String::from("[\n    1, # [0]\n    2, # [1]\n]")),
+        to_string_pretty(&vec![1, 2], PrettyConfig::new().enumerate_arrays(true))
+    );
+}
+
+#[test]
+fn test_cavetta_maps() {
+    let mut map = BTreeMap::new();
+    map.insert(Value::Number(Number::new(1)), Value::Bool(true));
+    map.insert(Value::String(String::from("name")), Value::Char('z'));
+    map.insert(Value::Seq(vec![Value::Unit]), Value::Option(None));
+
+    assert_eq!(
+        Ok(String::from("{\n    <1> true,\n    <\"name\"> 'z',\n    <[\n        (),\n    ]> None,\n}")),
+        to_string_pretty(&map, PrettyConfig::new().cavetta_maps(CavettaMaps::Always))
+    );
+    assert_eq!(
+        Ok(String::from("{<1> true, \"name\": 'z', <[()]> None}")),
+        to_string_pretty(&map, PrettyConfig::new().cavetta_maps(CavettaMaps::NonIdentifierKeys).depth_limit(0))
+    );
+}
+
+#[test]
+fn test_comments() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Window {
+        title: String,
+        size: (u32, u32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        window: Window,
+        fonts: Vec<String>,
+        keys: BTreeMap<String, char>,
+    }
+
+    let config = Config {
+        window: Window { title: String::from("zmerald"), size: (640, 480) },
+        fonts: vec![String::from("mono"), String::from("serif")],
+        keys: vec![(String::from("quit"), 'q')].into_iter().collect(),
+    };
+
+    let comments: CommentMap = vec![
+        ("", "Generated defaults\n\nEdit freely"),
+        ("window", "The main window"),
+        ("window.title", "Shown in the title bar"),
+        ("window.size[0]", "Width"),
+        ("fonts[1]", "Fallback"),
+        ("keys.quit", "Closes the window"),
+    ].into_iter().collect();
+
+    let pretty = PrettyConfig::new().separate_tuple_members(true);
+    let serialized = to_string_with_comments(&config, &comments, pretty).unwrap();
+
+    assert_eq!(serialized, "\
+# Generated defaults
+#
+# Edit freely
+{
+    # The main window
+    window: {
+        # Shown in the title bar
+        title: \"zmerald\",
+        size: (
+            # Width
+            640,
+            480,
+        ),
+    },
+    fonts: [
+        \"mono\",
+        # Fallback
+        \"serif\",
+    ],
+    keys: {
+        # Closes the window
+        \"quit\": 'q',
+    },
+}");
+
+    let (deserialized, source_map) = from_str_with_source_map::<Config>(&serialized).unwrap();
+    assert_eq!(deserialized, config);
+
+    for (path, _) in comments.iter() {
+        assert!(source_map.get(path).is_some(), "{:?} is not a path of the document", path);
+    }
+}
+
+#[test]
+fn test_comments_on_one_line() {
+    let comments: CommentMap = vec![("[0]", "kept"), ("[1][0]", "dropped")].into_iter().collect();
+    let value = vec![vec![1], vec![2]];
+
+    assert_eq!(
+        Ok(String::from("[\n    # kept\n    [1],\n    [2],\n]")),
+        to_string_with_comments(&value, &comments, PrettyConfig::new().depth_limit(1))
+    );
+    assert_eq!(
+        Ok(String::from("[[1], [2]]")),
+        to_string_with_comments(&value, &comments, PrettyConfig::new().compact_arrays(true))
+    );
+}
+
+#[test]
+fn test_max_width() {
+    #[derive(Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Serialize)]
+    struct Shape {
+        name: String,
+        points: Vec<Point>,
+        tags: Vec<&'static str>,
+    }
+
+    let shape = Shape {
+        name: String::from("triangle"),
+        points: vec![Point { x: 0, y: 0 }, Point { x: 10, y: 0 }, Point { x: 5, y: 8 }],
+        tags: vec!["closed", "convex"],
+    };
+
+    assert_eq!(
+        to_string_pretty(&shape, PrettyConfig::new().max_width(40)).unwrap(),
+        "\
+{
+    name: \"triangle\",
+    points: [
+        {x: 0, y: 0},
+        {x: 10, y: 0},
+        {x: 5, y: 8},
+    ],
+    tags: [\"closed\", \"convex\"],
+}"
+    );
+
+    assert_eq!(
+        to_string_pretty(&shape, PrettyConfig::new().max_width(120)).unwrap(),
+        "{name: \"triangle\", points: [{x: 0, y: 0}, {x: 10, y: 0}, {x: 5, y: 8}], tags: [\"closed\", \"convex\"]}"
+    );
+
+    // The line ends exactly at the limit, trailing comma included
+    assert_eq!(
+        to_string_pretty(&vec![vec![1, 2], vec![3, 4]], PrettyConfig::new().max_width(8).indentor(String::from("\t"))).unwrap(),
+        "[\n\t[1, 2],\n\t[3, 4],\n]"
+    );
+    assert_eq!(
+        to_string_pretty(&vec![vec![1, 2], vec![3, 4]], PrettyConfig::new().max_width(7).indentor(String::from("\t"))).unwrap(),
+        "[\n\t[\n\t\t1,\n\t\t2,\n\t],\n\t[\n\t\t3,\n\t\t4,\n\t],\n]"
+    );
+}
+
+#[test]
+fn test_canonical() {
+    let forwards: HashMap<i32, f64> = (0..100).map(|i| (i, -0.0)).collect();
+    let backwards: HashMap<i32, f64> = (0..100).rev().map(|i| (i, 0.0)).collect();
+
+    let canonical = to_string_canonical(&forwards).unwrap();
+    assert_eq!(canonical, to_string_canonical(&backwards).unwrap());
+    assert!(canonical.starts_with("{0:0.0,1:0.0,2:0.0,"));
+    assert_eq!(fingerprint(&forwards), fingerprint(&backwards));
+
+    // Keys sort as values: numbers before strings, and numbers by value
+    let mut keys = HashMap::new();
+    keys.insert(Value::String(String::from("b")), 1);
+    keys.insert(Value::Number(Number::new(10)), 2);
+    keys.insert(Value::Number(Number::new(9)), 3);
+    keys.insert(Value::Bool(true), 4);
+    assert_eq!(Ok(String::from("{true:4,9:3,10:2,\"b\":1}")), to_string_canonical(&keys));
+
+    let mut pairs = HashMap::new();
+    pairs.insert("b", (3, 4));
+    pairs.insert("a", (1, 2));
+    assert_eq!(
+        Ok(String::from("{\n    \"a\": (\n        1,\n        2\n    ),\n    \"b\": (\n        3,\n        4\n    )\n}")),
+        to_string_pretty(&pairs, PrettyConfig::new().canonical(true).separate_tuple_members(true))
+    );
+
+    assert_ne!(fingerprint(&pairs), fingerprint(&keys));
+    // The canonical form, and so the fingerprint, of a value never changes
+    assert_eq!(Ok(String::from("{\"a\":(1,2),\"b\":(3,4)}")), to_string_canonical(&pairs));
+    assert_eq!(Ok(0x4485_ff7f_46cf_f92c), fingerprint(&pairs));
+}
+
+#[test]
+fn test_diff() {
+    let defaults = Settings::default();
+
+    let mut settings = defaults.clone();
+    settings.appearance.font.size = 12.0;
+    settings.recent.push(Font::default());
+
+    assert_eq!(
+        to_string_diff(&settings, &defaults, PrettyConfig::new()).unwrap(),
+        "\
+{
+    appearance: {
+        font: {
+            size: 12.0,
+        },
+    },
+    recent: [
+        {
+            family: \"\",
+            size: 0.0,
+        },
+    ],
+}"
+    );
+
+    assert_eq!(Ok(String::from("{}")), to_string_diff(&defaults, &defaults, PrettyConfig::new().depth_limit(0)));
+
+    let mut rng = Rng(0x5eed_0000_0000_0003);
+
+    for _ in 0..200 {
+        let settings = rng.settings();
+
+        for config in configs().into_iter().flatten() {
+            let diff = to_string_diff(&settings, &defaults, config.clone()).unwrap();
+
+            // Fields are compared by their canonical form, in which `-0.0` is `0.0`
+            check_reads_back(&settings, &diff, true, &Some(config));
+        }
+    }
+}
+
+#[test]
+fn round_trip_lenient() {
+    let mut rng = Rng(0x5eed_0000_0000_0004);
+
+    for _ in 0..50 {
+        let everything = rng.everything();
+        check_round_trip_with(&everything, bare_strings_configs());
+        check_round_trip_with(&everything, unwrap_newtypes_configs());
+
+        let everything = Everything { value: Value::Unit, ..everything };
+        check_round_trip_with(&everything, implicit_some_configs());
+    }
+}
+
+#[test]
+fn test_bare_strings() {
+    let strings = vec!["rald", "two words", "None", "", "a,b", "わ", "#", "say \"hi\""];
+
+    assert_eq!(
+        Ok(String::from("[\"rald\", \"two words\", \"None\", \"\", \"a,b\", わ, \"#\", \"say \\\"hi\\\"\"]")),
+        to_string_pretty(&strings, PrettyConfig::new().bare_strings(true).compact_arrays(true))
+    );
+
+    // An untyped `Value` reads an identifier as a unit struct, so it is quoted
+    let value = Value::Seq(vec![Value::String(String::from("abc")), Value::String(String::from("わa-b"))]);
+    assert_eq!("[\"abc\", わa-b]", check_written(&value, Some(PrettyConfig::new().bare_strings(true).compact_arrays(true))));
+}
+
+#[test]
+fn test_implicit_some() {
+    let options: Vec<Option<Option<Option<u8>>>> = vec![None, Some(None), Some(Some(None)), Some(Some(Some(1)))];
+    let config = PrettyConfig::new().implicit_some(true).compact_arrays(true);
+
+    assert_eq!(
+        Ok(String::from("[None, Some(None), Some(Some(None)), 1]")),
+        to_string_pretty(&options, config.clone())
+    );
+    assert_eq!(
+        Ok(String::from("[None,Some(None),Some(Some(None)),Some(Some(Some(1)))]")),
+        to_string(&options)
+    );
+
+    let strings = vec![Some(String::from("None")), Some(String::from("Some")), None];
+    assert_eq!(
+        Ok(String::from("[\"None\", \"Some\", None]")),
+        to_string_pretty(&strings, config.bare_strings(true))
+    );
+}
+
+#[test]
+fn test_to_writer_many() {
+    let values = vec![
+        Named { a: true, b: Some('x'), c: vec![0.5], kind: String::from("a") },
+        Named { a: false, b: None, c: vec![], kind: String::from("---") },
+    ];
+
+    let mut output = Vec::new();
+    to_writer_many(&mut output, &values, None).unwrap();
+    to_writer_many(&mut output, &values, Some(PrettyConfig::new().bare_strings(true))).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let read = crate::de::StreamDeserializer::<Named>::from_str(&output)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(same(&[&values[..], &values[..]].concat(), &read));
+    assert_eq!(2, output.matches("\n---\n").count());
+}
+
+#[test]
+fn test_unwrap_newtypes() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Px(u32);
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    enum Size {
+        Abs(Px),
+        Rel(f32),
+        Fill,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Layout {
+        gap: Px,
+        sizes: Vec<Size>,
+    }
+
+    let layout = Layout { gap: Px(8), sizes: vec![Size::Abs(Px(100)), Size::Rel(0.5), Size::Fill] };
+
+    let config = PrettyConfig::new().unwrap_newtypes(true).unwrap_variant_newtypes(true);
+    assert_eq!(
+        "#![enable(unwrap_newtypes, unwrap_variant_newtypes)]\n{\n    gap: 8,\n    sizes: [\n        Abs 100,\n        Rel 0.5,\n        Fill,\n    ],\n}",
+        check_written(&layout, Some(config))
+    );
+    assert_eq!(
+        "#![enable(unwrap_variant_newtypes)]\n{\n    gap: (8),\n    sizes: [\n        Abs (100),\n        Rel 0.5,\n        Fill,\n    ],\n}",
+        check_written(&layout, Some(PrettyConfig::new().unwrap_variant_newtypes(true)))
+    );
+
+    // Without the pragma, the options say how to read
+    let options = crate::de::DeserializerOptions::new().unwrap_newtypes(true).unwrap_variant_newtypes(true);
+    assert_eq!(layout, crate::de::from_str_with_options::<Layout>("{gap: 8, sizes: [Abs 100, Rel 0.5, Fill]}", options).unwrap());
+    assert!(from_str::<Layout>("{gap: 8, sizes: []}").is_err());
+
+    // With either extension on, the explicit forms still read
+    let newtypes = crate::de::DeserializerOptions::new().unwrap_newtypes(true);
+    let variants = crate::de::DeserializerOptions::new().unwrap_variant_newtypes(true);
+    for options in [newtypes, variants, options] {
+        for source in ["{gap: Px(8), sizes: [Abs(Px(100)), Rel(0.5), Fill]}", "{gap: (8), sizes: [Abs((100)), Rel(0.5), Fill]}"] {
+            assert_eq!(layout, crate::de::from_str_with_options::<Layout>(source, options).unwrap());
+        }
+    }
+    assert_eq!(layout, crate::de::from_str_with_options::<Layout>("{gap: Px(8), sizes: [Abs 100, Rel(0.5), Fill]}", options).unwrap());
+
+    let mut output = Vec::new();
+    to_writer_many(&mut output, [Px(1), Px(2)], Some(PrettyConfig::new().unwrap_newtypes(true))).unwrap();
+    assert_eq!("#![enable(unwrap_newtypes)]\n1\n---\n2\n", String::from_utf8(output).unwrap());
+}
@@ -1,6 +1,7 @@
-use serde::ser::{Serialize, Serializer};
+use serde::ser::{self, Serialize, Serializer};
 
-use crate::value::{Number, Value};
+use crate::error::Error;
+use crate::value::{BorrowedValue, Map, Number, Value};
 
 impl Serialize for Value {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -19,3 +20,306 @@ impl Serialize for Value {
         }
     }
 }
+
+impl<'a> Serialize for BorrowedValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        match *self {
+            BorrowedValue::Bool(b) => serializer.serialize_bool(b),
+            BorrowedValue::Char(c) => serializer.serialize_char(c),
+            BorrowedValue::Map(ref m) => Serialize::serialize(m, serializer),
+            BorrowedValue::Number(Number::Float(ref f)) => serializer.serialize_f64(f.get()),
+            BorrowedValue::Number(Number::Integer(i)) => serializer.serialize_i64(i),
+            BorrowedValue::Option(Some(ref o)) => serializer.serialize_some(o.as_ref()),
+            BorrowedValue::Option(None) => serializer.serialize_none(),
+            BorrowedValue::String(ref s) => serializer.serialize_str(s),
+            BorrowedValue::Seq(ref s) => Serialize::serialize(s, serializer),
+            BorrowedValue::Unit => serializer.serialize_unit(),
+        }
+    }
+}
+
+/// Converts a serializable value to the `Value` it reads back as, where it
+/// can. Enum variants, which `Value` has no room for, become their name or a
+/// map from their name to their content.
+pub(crate) fn to_value<T>(value: &T) -> Result<Value, Error>
+where T: ?Sized + Serialize {
+    value.serialize(ValueSerializer)
+}
+
+fn variant(variant: &'static str, value: Value) -> Value {
+    Value::Map(std::iter::once((Value::String(String::from(variant)), value)).collect())
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = SeqBuilder;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = MapBuilder;
+    type SerializeStructVariant = MapBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Number(Number::new(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::Number(Number::new(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Number(Number::new(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::String(String::from(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::String(base64::encode(v)))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Option(None))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value, Error>
+    where T: ?Sized + Serialize {
+        Ok(Value::Option(Some(Box::new(to_value(value)?))))
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Value, Error> {
+        Ok(Value::String(String::from(variant)))
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<Value, Error>
+    where T: ?Sized + Serialize {
+        to_value(value)
+    }
+
+    fn serialize_newtype_variant<T>(self, _: &'static str, _: u32, name: &'static str, value: &T) -> Result<Value, Error>
+    where T: ?Sized + Serialize {
+        Ok(variant(name, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, Error> {
+        Ok(SeqBuilder { seq: Vec::with_capacity(len.unwrap_or(0)), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<SeqBuilder, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<SeqBuilder, Error> {
+        Ok(SeqBuilder { seq: Vec::with_capacity(len), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder { map: Map::new(), key: None, variant: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<MapBuilder, Error> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str, _: usize) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder { map: Map::new(), key: None, variant: Some(variant) })
+    }
+}
+
+struct SeqBuilder {
+    seq: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SeqBuilder {
+    fn push<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.seq.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        let seq = Value::Seq(self.seq);
+        Ok(match self.variant {
+            Some(name) => variant(name, seq),
+            None => seq,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+struct MapBuilder {
+    map: Map,
+    key: Option<Value>,
+    variant: Option<&'static str>,
+}
+
+impl MapBuilder {
+    fn finish(self) -> Result<Value, Error> {
+        let map = Value::Map(self.map);
+        Ok(match self.variant {
+            Some(name) => variant(name, map),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for MapBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.key = Some(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        let key = self.key.take().ok_or_else(|| Error::Message(String::from("map value without a key")))?;
+        self.map.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        self.map.insert(Value::String(String::from(key)), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapBuilder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where T: ?Sized + Serialize {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::Span;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// One step of the path from the document root to a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PathSegment<'a> {
+    /// A struct field or map key, as written in the source.
+    Key(&'a [u8]),
+    /// An element of a sequence or tuple.
+    Index(usize),
+}
+
+/// Strips the quotes of a key written as a string without escapes.
+pub(crate) fn unquoted(key: &[u8]) -> &[u8] {
+    match key {
+        [b'"', inner @ .., b'"'] if !inner.contains(&b'\\') => inner,
+        key => key,
+    }
+}
+
+/// Formats a path the way it is looked up in a `SourceMap`, e.g. `keys.49[2]`.
+pub(crate) struct PathDisplay<'a, 'b>(pub &'a [PathSegment<'b>]);
+
+impl fmt::Display for PathDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match *segment {
+                PathSegment::Key(key) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+
+                    f.write_str(&String::from_utf8_lossy(unquoted(key)))?;
+                }
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The span of every value of a document, keyed by its path.
+///
+/// Paths join struct fields and map keys with `.` and write sequence indices
+/// in brackets, e.g. `keys.49[2]`. The document root has the empty path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    spans: BTreeMap<String, Span>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Span> {
+        self.spans.get(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, &Span)> {
+        self.spans.iter().map(|(path, span)| (path.as_str(), span))
+    }
+
+    pub(crate) fn insert(&mut self, path: &[PathSegment<'_>], span: Span) {
+        self.spans.insert(PathDisplay(path).to_string(), span);
+    }
+}
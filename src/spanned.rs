@@ -0,0 +1,171 @@
+use crate::error::{ Error, Position, Result };
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{ Hash, Hasher };
+use std::marker::PhantomData;
+use serde::de::{ self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor };
+use serde::de::value::{ BorrowedStrDeserializer, MapDeserializer };
+use serde::{ Deserialize, Deserializer, Serialize, Serializer };
+
+// The deserializers recognise this struct name and hand out the positions of
+// the wrapped value as if they were fields.
+pub(crate) const NAME: &str = "$__zmerald_private_Spanned";
+pub(crate) const START: &str = "$__zmerald_private_start";
+pub(crate) const END: &str = "$__zmerald_private_end";
+pub(crate) const VALUE: &str = "$__zmerald_private_value";
+pub(crate) const FIELDS: &[&str] = &[START, END, VALUE];
+
+/// A value together with the position it was read from.
+///
+/// The span runs from the first byte of the value up to, but not including,
+/// the byte after it. Values deserialized from a `Value` rather than from
+/// source text carry the zero position `0:0`.
+#[derive(Clone, Debug)]
+pub struct Spanned<T> {
+    start: Position,
+    end: Position,
+    value: T,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(start: Position, end: Position, value: T) -> Self {
+        Spanned { start, end, value }
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+// Two spanned values are the same when the values are, wherever they came from.
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.eq(&other.value)
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Spanned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Ord> Ord for Spanned<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<T: Serialize> Serialize for Spanned<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Spanned<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        struct SpannedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for SpannedVisitor<T> {
+            type Value = Spanned<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a spanned value")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error> where A: MapAccess<'de> {
+                if map.next_key()? != Some(START) {
+                    return Err(de::Error::custom("spanned start key not found"));
+                }
+                let start = map.next_value()?;
+
+                if map.next_key()? != Some(VALUE) {
+                    return Err(de::Error::custom("spanned value key not found"));
+                }
+                let value = map.next_value()?;
+
+                if map.next_key()? != Some(END) {
+                    return Err(de::Error::custom("spanned end key not found"));
+                }
+                let end = map.next_value()?;
+
+                Ok(Spanned { start, end, value })
+            }
+        }
+
+        deserializer.deserialize_struct(NAME, FIELDS, SpannedVisitor(PhantomData))
+    }
+}
+
+/// Feeds a `Spanned` from a deserializer which has no source positions.
+pub(crate) struct UnspannedAccess<D> {
+    value: Option<D>,
+    state: usize,
+}
+
+impl<D> UnspannedAccess<D> {
+    pub(crate) fn new(value: D) -> Self {
+        UnspannedAccess { value: Some(value), state: 0 }
+    }
+}
+
+impl<'de, D> MapAccess<'de> for UnspannedAccess<D> where D: de::Deserializer<'de, Error = Error> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>> where K: DeserializeSeed<'de> {
+        let key = match self.state {
+            0 => START,
+            1 => VALUE,
+            2 => END,
+            _ => return Ok(None),
+        };
+
+        seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value> where V: DeserializeSeed<'de> {
+        self.state += 1;
+
+        match self.state {
+            2 => match self.value.take() {
+                Some(value) => seed.deserialize(value),
+                None => Err(Error::Message(String::from("spanned value was already taken"))),
+            },
+            _ => seed.deserialize(Position { line: 0, col: 0 }.into_deserializer()),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Position {
+    type Deserializer = MapDeserializer<'de, std::array::IntoIter<(&'static str, usize), 2>, Error>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        MapDeserializer::new([("line", self.line), ("col", self.col)].into_iter())
+    }
+}
@@ -0,0 +1,45 @@
+use super::{ Map, Number, Value };
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// A `Value` whose strings borrow from the parsed input where possible.
+///
+/// Unquoted and escape-free strings are handed out as `Cow::Borrowed`, only
+/// strings containing escapes are allocated.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BorrowedValue<'a> {
+    Bool(bool),
+    Char(char),
+    Map(BTreeMap<BorrowedValue<'a>, BorrowedValue<'a>>),
+    Number(Number),
+    Option(Option<Box<BorrowedValue<'a>>>),
+    String(Cow<'a, str>),
+    Seq(Vec<BorrowedValue<'a>>),
+    Unit,
+}
+
+impl<'a> BorrowedValue<'a> {
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Bool(b) => Value::Bool(b),
+            BorrowedValue::Char(c) => Value::Char(c),
+            BorrowedValue::Map(m) => Value::Map(
+                m.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect::<Map>(),
+            ),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::Option(o) => Value::Option(o.map(|v| Box::new(v.into_owned()))),
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Seq(s) => Value::Seq(s.into_iter().map(BorrowedValue::into_owned).collect()),
+            BorrowedValue::Unit => Value::Unit,
+        }
+    }
+}
+
+impl<'a> From<BorrowedValue<'a>> for Value {
+    fn from(value: BorrowedValue<'a>) -> Self {
+        value.into_owned()
+    }
+}
@@ -1,11 +1,13 @@
 use super::Value;
 
 use crate::error::{ Error, Result };
+use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::collections::btree_map::Entry;
 use std::hash::{ Hasher, Hash };
 use std::ops::{ Index, IndexMut };
 use serde::{ Deserialize, Serialize };
-use serde::de::{ DeserializeSeed, MapAccess };
+use serde::de::{ DeserializeSeed, Deserializer, MapAccess };
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(transparent)]
@@ -28,31 +30,61 @@ impl Map {
         self.0.insert(key, value)
     }
 
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        self.0.get_mut(key)
+    }
+
+    pub fn entry(&mut self, key: Value) -> Entry<'_, Value, Value> {
+        self.0.entry(key)
+    }
+
     pub fn remove(&mut self, key: &Value) -> Option<Value> {
         self.0.remove(key)
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> + DoubleEndedIterator {
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Value, &Value)> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Value, &mut Value)> + DoubleEndedIterator {
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&Value, &mut Value)> {
         self.0.iter_mut()
     }
 
-    pub fn keys(&self) -> impl Iterator<Item = &Value> + DoubleEndedIterator {
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &Value> {
         self.0.keys()
     }
 
-    pub fn values(&self) -> impl Iterator<Item = &Value> + DoubleEndedIterator {
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &Value> {
         self.0.values()
     }
 
-    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> + DoubleEndedIterator {
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Value> {
         self.0.values_mut()
     }
 }
 
+impl IntoIterator for Map {
+    type Item = (Value, Value);
+    type IntoIter = <MapInner as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a Value, &'a Value);
+    type IntoIter = <&'a MapInner as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl FromIterator<(Value, Value)> for Map {
     fn from_iter<T: IntoIterator<Item = (Value, Value)>>(iter: T) -> Self {
         Map(MapInner::from_iter(iter))
@@ -75,6 +107,8 @@ impl Index<&Value> for Map {
     }
 }
 
+/// Panics if the key is not in the map, like `Index`. Use `get_mut` or
+/// `entry` to handle a missing key.
 impl IndexMut<&Value> for Map {
     fn index_mut(&mut self, index: &Value) -> &mut Self::Output {
         self.0.get_mut(index).expect("no entry found for key")
@@ -89,41 +123,64 @@ impl Ord for Map {
 
 impl PartialEq for Map {
     fn eq(&self, other: &Map) -> bool {
-        self.iter().zip(other.iter()).all(|(a, b)| a == b)
+        self.0 == other.0
     }
 }
 
 impl PartialOrd for Map {
     fn partial_cmp(&self, other: &Map) -> Option<Ordering> {
-        self.iter().partial_cmp(other.iter())
+        Some(self.cmp(other))
     }
 }
 
 type MapInner = std::collections::BTreeMap<Value, Value>;
 // type MapInner = indexmap::IndexMap<Value, Value>;
 
-pub struct MapAccessor {
-    pub keys: Vec<Value>,
-    pub values: Vec<Value>,
+pub struct MapAccessor<I, V> {
+    iter: I,
+    value: Option<V>,
+    // The key of `value` as it appears in error paths
+    key: String,
+}
+
+impl<I, V> MapAccessor<I, V> {
+    pub fn new(iter: I) -> Self {
+        MapAccessor { iter, value: None, key: String::new() }
+    }
 }
 
-impl<'de> MapAccess<'de> for MapAccessor {
+impl<'de, I, V> MapAccess<'de> for MapAccessor<I, V>
+where I: Iterator<Item = (V, V)>, V: Deserializer<'de, Error = Error> + Borrow<Value> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where K: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
-        self.keys
-            .pop()
-            .map_or(Ok(None), |v| seed.deserialize(v).map(Some))
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.key = match key.borrow() {
+                    Value::String(s) => s.clone(),
+                    key => crate::ser::to_string(key)?,
+                };
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<K>(&mut self, seed: K) -> Result<K::Value>
+    where K: DeserializeSeed<'de> {
+        let value = self.value
+            .take()
+            .ok_or_else(|| Error::Message(String::from("map value requested before its key")))?;
+
+        seed.deserialize(value).map_err(|e| e.at(std::mem::take(&mut self.key)))
     }
 
-    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
-    where V: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
-        self.values
-            .pop()
-            .map(|v| seed.deserialize(v))
-            .expect("Contract violation")
+    fn size_hint(&self) -> Option<usize> {
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+            _ => None,
+        }
     }
-}
\ No newline at end of file
+}
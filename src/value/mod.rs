@@ -7,10 +7,21 @@ pub use arithmetic::Number;
 mod sequence;
 pub use sequence::Seq;
 
-use serde::de::{ DeserializeOwned, Deserializer, Visitor };
+mod borrowed;
+pub use borrowed::BorrowedValue;
+
+mod raw;
+pub(crate) use raw::RAW_VALUE_TOKEN;
+pub use raw::RawValue;
+
+#[cfg(test)]
+mod tests;
+
+use serde::de::{ self, Deserialize, DeserializeOwned, Deserializer, Unexpected, Visitor };
 use serde::forward_to_deserialize_any;
 
-use crate::error::{ Error, Result };
+use crate::error::{ Error, Result, SpannedError, SpannedResult };
+use crate::spanned::{ self, UnspannedAccess };
 use std::cmp::Eq;
 use std::hash::Hash;
 
@@ -30,84 +41,170 @@ impl Value {
     pub fn into_rust<T>(self) -> Result<T> where T: DeserializeOwned {
         T::deserialize(self)
     }
+
+    pub fn to_rust<'de, T>(&'de self) -> Result<T> where T: Deserialize<'de> {
+        T::deserialize(self)
+    }
+
+    /// Like `into_rust`, but errors carry the path to where they occurred as
+    /// a field, with no position, as there is no source to point into.
+    #[allow(clippy::result_large_err)]
+    pub fn into_rust_spanned<T>(self) -> SpannedResult<T> where T: DeserializeOwned {
+        self.into_rust().map_err(SpannedError::from)
+    }
+
+    /// Like `to_rust`, with errors as in `into_rust_spanned`.
+    #[allow(clippy::result_large_err)]
+    pub fn to_rust_spanned<'de, T>(&'de self) -> SpannedResult<T> where T: Deserialize<'de> {
+        self.to_rust().map_err(SpannedError::from)
+    }
+}
+
+/// The methods of `Deserializer` that read an owned `Value` and a borrowed
+/// one alike.
+macro_rules! deserialize_value_common {
+    () => {
+        forward_to_deserialize_any! {
+            bool f32 f64 char str string bytes
+            byte_buf unit unit_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+
+        fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            if name == RAW_VALUE_TOKEN {
+                // There is no source left to capture, so write the value out again
+                visitor.visit_string(crate::ser::to_string(&self)?)
+            } else {
+                visitor.visit_newtype_struct(self)
+            }
+        }
+
+        fn deserialize_struct<V>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            if name == spanned::NAME && fields == spanned::FIELDS {
+                visitor.visit_map(UnspannedAccess::new(self))
+            } else {
+                self.deserialize_any(visitor).map_err(|e| e.with_outer(name))
+            }
+        }
+
+        fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_i64(visitor)
+        }
+
+        fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_i64(visitor)
+        }
+
+        fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_i64(visitor)
+        }
+
+        fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            visitor.visit_i64(integer(&self)?)
+        }
+
+        fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_u64(visitor)
+        }
+
+        fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_u64(visitor)
+        }
+
+        fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            self.deserialize_u64(visitor)
+        }
+
+        fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de> {
+            let i = integer(&self)?;
+
+            match u64::try_from(i) {
+                Ok(u) => visitor.visit_u64(u),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Signed(i), &visitor)),
+            }
+        }
+    };
+}
+
+fn integer(value: &Value) -> Result<i64> {
+    match value {
+        Value::Number(Number::Integer(i)) => Ok(*i),
+        v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
+    }
 }
 
 impl<'de> Deserializer<'de> for Value {
     type Error = Error;
 
-    forward_to_deserialize_any! {
-        bool f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
-    }
+    deserialize_value_common!();
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         match self {
             Value::Bool(b) => visitor.visit_bool(b),
             Value::Char(c) => visitor.visit_char(c),
-            Value::Map(m) => visitor.visit_map(MapAccessor {
-                keys: m.keys().cloned().rev().collect(),
-                values: m.values().cloned().rev().collect(),
-            }),
+            Value::Map(m) => visitor.visit_map(MapAccessor::new(m.into_iter())),
             Value::Number(Number::Float(ref f)) => visitor.visit_f64(f.get()),
             Value::Number(Number::Integer(i)) => visitor.visit_i64(i),
             Value::Option(Some(o)) => visitor.visit_some(*o),
             Value::Option(None) => visitor.visit_none(),
             Value::String(s) => visitor.visit_string(s),
-            Value::Seq(mut seq) => {
-                seq.reverse();
-                visitor.visit_seq(Seq { seq })
-            }
+            Value::Seq(seq) => visitor.visit_seq(Seq::new(seq.into_iter())),
             Value::Unit => visitor.visit_unit(),
         }
     }
 
-    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
-    where V: Visitor<'de> {
-        self.deserialize_i64(visitor)
-    }
-
-    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
-    where V: Visitor<'de> {
-        self.deserialize_i64(visitor)
-    }
-
-    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
-    where V: Visitor<'de> {
-        self.deserialize_i64(visitor)
-    }
-
-    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
+        // Mirrors the text deserializer, where a bare value is an implicit `Some`
         match self {
-            Value::Number(Number::Integer(i)) => visitor.visit_i64(i),
-            v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
+            Value::Option(Some(o)) => visitor.visit_some(*o),
+            Value::Option(None) => visitor.visit_none(),
+            v => visitor.visit_some(v),
         }
     }
+}
 
-    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
-    where V: Visitor<'de> {
-        self.deserialize_u64(visitor)
-    }
+// Deserializing from a borrowed tree hands out `&'de str`s pointing into the
+// `Value` itself, so nothing is cloned on the way.
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = Error;
 
-    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
-    where V: Visitor<'de> {
-        self.deserialize_u64(visitor)
-    }
+    deserialize_value_common!();
 
-    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
-        self.deserialize_u64(visitor)
+        match self {
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Char(c) => visitor.visit_char(*c),
+            Value::Map(m) => visitor.visit_map(MapAccessor::new(m.iter())),
+            Value::Number(Number::Float(f)) => visitor.visit_f64(f.get()),
+            Value::Number(Number::Integer(i)) => visitor.visit_i64(*i),
+            Value::Option(Some(o)) => visitor.visit_some(&**o),
+            Value::Option(None) => visitor.visit_none(),
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Seq(seq) => visitor.visit_seq(Seq::new(seq.iter())),
+            Value::Unit => visitor.visit_unit(),
+        }
     }
 
-    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
+        // Mirrors the text deserializer, where a bare value is an implicit `Some`
         match self {
-            Value::Number(Number::Integer(i)) => visitor.visit_u64(i as u64),
-            v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
+            Value::Option(Some(o)) => visitor.visit_some(&**o),
+            Value::Option(None) => visitor.visit_none(),
+            v => visitor.visit_some(v),
         }
     }
 }
-
-//COLOUUUUUUUUUUUURS YOUPI 
\ No newline at end of file
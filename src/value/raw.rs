@@ -0,0 +1,160 @@
+use crate::de::Deserializer;
+use crate::error::SpannedResult;
+
+use std::fmt;
+use serde::{ de, ser, Deserialize, Serialize };
+
+// The (de)serializers recognise this newtype name and pass the source
+// through untouched.
+pub(crate) const RAW_VALUE_TOKEN: &str = "$__zmerald_private_RawValue";
+
+/// An unparsed piece of zmerald source, kept exactly as it was written.
+///
+/// Deserializing a `&RawValue` borrows the slice of the input holding the
+/// value, comments and formatting included. Serializing writes it back
+/// verbatim.
+#[repr(transparent)]
+pub struct RawValue {
+    zmerald: str,
+}
+
+impl RawValue {
+    fn from_borrowed_str(zmerald: &str) -> &Self {
+        // Safety: RawValue is a transparent newtype around str
+        unsafe { &*(zmerald as *const str as *const RawValue) }
+    }
+
+    fn from_boxed_str(zmerald: Box<str>) -> Box<Self> {
+        // Safety: RawValue is a transparent newtype around str
+        unsafe { Box::from_raw(Box::into_raw(zmerald) as *mut RawValue) }
+    }
+
+    /// Checks that `zmerald` holds exactly one value and wraps it.
+    #[allow(clippy::result_large_err)]
+    pub fn from_zmerald(zmerald: &str) -> SpannedResult<&Self> {
+        let mut deserializer = Deserializer::from_str(zmerald)?;
+        de::IgnoredAny::deserialize(&mut deserializer).map_err(|e| deserializer.span_error(e))?;
+        deserializer.end().map_err(|e| deserializer.span_error(e))?;
+
+        Ok(Self::from_borrowed_str(zmerald))
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn from_boxed_zmerald(zmerald: Box<str>) -> SpannedResult<Box<Self>> {
+        Self::from_zmerald(&zmerald)?;
+
+        Ok(Self::from_boxed_str(zmerald))
+    }
+
+    pub fn get_zmerald(&self) -> &str {
+        &self.zmerald
+    }
+
+    /// Parses the captured source into a `T`.
+    #[allow(clippy::result_large_err)]
+    pub fn into_rust<'de, T>(&'de self) -> SpannedResult<T> where T: Deserialize<'de> {
+        crate::de::from_str(&self.zmerald)
+    }
+}
+
+impl Clone for Box<RawValue> {
+    fn clone(&self) -> Self {
+        (**self).to_owned()
+    }
+}
+
+impl ToOwned for RawValue {
+    type Owned = Box<RawValue>;
+
+    fn to_owned(&self) -> Self::Owned {
+        RawValue::from_boxed_str(self.zmerald.to_owned().into_boxed_str())
+    }
+}
+
+impl PartialEq for RawValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.zmerald == other.zmerald
+    }
+}
+
+impl Eq for RawValue {}
+
+impl fmt::Debug for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawValue").field(&&self.zmerald).finish()
+    }
+}
+
+impl fmt::Display for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.zmerald)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: ser::Serializer {
+        serializer.serialize_newtype_struct(RAW_VALUE_TOKEN, &self.zmerald)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a RawValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: de::Deserializer<'de> {
+        struct ReferenceVisitor;
+
+        impl<'de> de::Visitor<'de> for ReferenceVisitor {
+            type Value = &'de RawValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a borrowed zmerald value")
+            }
+
+            fn visit_borrowed_str<E>(self, zmerald: &'de str) -> Result<Self::Value, E> where E: de::Error {
+                Ok(RawValue::from_borrowed_str(zmerald))
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where D: de::Deserializer<'de> {
+                deserializer.deserialize_str(self)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, ReferenceVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<RawValue> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: de::Deserializer<'de> {
+        struct BoxedVisitor;
+
+        impl<'de> de::Visitor<'de> for BoxedVisitor {
+            type Value = Box<RawValue>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a zmerald value")
+            }
+
+            fn visit_str<E>(self, zmerald: &str) -> Result<Self::Value, E> where E: de::Error {
+                Ok(RawValue::from_borrowed_str(zmerald).to_owned())
+            }
+
+            fn visit_string<E>(self, zmerald: String) -> Result<Self::Value, E> where E: de::Error {
+                Ok(RawValue::from_boxed_str(zmerald.into_boxed_str()))
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where D: de::Deserializer<'de> {
+                deserializer.deserialize_string(self)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, BoxedVisitor)
+    }
+}
+
+impl From<Box<RawValue>> for Box<str> {
+    fn from(raw: Box<RawValue>) -> Self {
+        // Safety: RawValue is a transparent newtype around str
+        unsafe { Box::from_raw(Box::into_raw(raw) as *mut str) }
+    }
+}
+
@@ -1,20 +1,35 @@
-use super::Value;
-
 use crate::error::{ Error, Result };
-use serde::de::{ DeserializeSeed, SeqAccess };
+use serde::de::{ DeserializeSeed, Deserializer, SeqAccess };
+
+pub struct Seq<I> {
+    iter: I,
+    index: usize,
+}
 
-pub struct Seq {
-    pub seq: Vec<Value>,
+impl<I> Seq<I> {
+    pub fn new(iter: I) -> Self {
+        Seq { iter, index: 0 }
+    }
 }
 
-impl<'de> SeqAccess<'de> for Seq {
+impl<'de, I, V> SeqAccess<'de> for Seq<I>
+where I: Iterator<Item = V>, V: Deserializer<'de, Error = Error> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where T: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
-        self.seq
-            .pop()
-            .map_or(Ok(None), |v| seed.deserialize(v).map(Some))
+        let index = self.index;
+        self.index += 1;
+
+        self.iter
+            .next()
+            .map_or(Ok(None), |v| seed.deserialize(v).map(Some).map_err(|e| e.at(format!("[{}]", index))))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+            _ => None,
+        }
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,210 @@
+use super::*;
+
+use crate::de::from_str;
+use std::collections::HashMap;
+use serde::Deserialize;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+struct Layout {
+    id: String,
+    keys: HashMap<u16, Vec<Option<String>>>,
+}
+
+const LAYOUT: &str = r#"{
+    "id": "bu",
+    "keys": {
+        49: [ "1", "2", "3" ],
+        69: [ "4", None ],
+    },
+}"#;
+
+fn layout() -> Layout {
+    Layout {
+        id: String::from("bu"),
+        keys: HashMap::from([
+            (49, vec![Some(1.to_string()), Some(2.to_string()), Some(3.to_string())]),
+            (69, vec![Some(4.to_string()), None]),
+        ]),
+    }
+}
+
+#[test]
+fn test_into_rust() {
+    let value: Value = from_str(LAYOUT).unwrap();
+    assert_eq!(Ok(layout()), value.into_rust());
+}
+
+#[test]
+fn test_to_rust() {
+    let value: Value = from_str(LAYOUT).unwrap();
+    assert_eq!(Ok(layout()), value.to_rust());
+
+    // The tree is left untouched and can be deserialized again.
+    assert_eq!(Ok(layout()), Layout::deserialize(&value));
+}
+
+#[test]
+fn test_to_rust_borrowed_str() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Borrowed<'a> {
+        id: &'a str,
+        tags: Vec<&'a str>,
+    }
+
+    let value: Value = from_str(r#"{ "id": "bu", "tags": [ "left", "right" ] }"#).unwrap();
+    let borrowed: Borrowed = value.to_rust().unwrap();
+    assert_eq!(Borrowed { id: "bu", tags: vec!["left", "right"] }, borrowed);
+
+    match &value {
+        Value::Map(map) => {
+            let id = &map[&Value::String(String::from("id"))];
+            match id {
+                Value::String(s) => assert_eq!(s.as_ptr(), borrowed.id.as_ptr()),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_seq_order() {
+    let value: Value = from_str("[1, 2, 3, 4]").unwrap();
+    assert_eq!(Ok(vec![1u8, 2, 3, 4]), value.to_rust::<Vec<u8>>());
+    assert_eq!(Ok(vec![1u8, 2, 3, 4]), value.into_rust::<Vec<u8>>());
+}
+
+#[test]
+fn test_negative_unsigned() {
+    let value = Value::Number(Number::new(-1));
+    assert!(value.to_rust::<u64>().is_err());
+    assert!(value.to_rust::<u8>().is_err());
+    assert!(value.into_rust::<u32>().is_err());
+
+    let value = Value::Seq(vec![Value::Number(Number::new(-1))]);
+    assert!(value.to_rust::<Vec<u64>>().is_err());
+}
+
+#[test]
+fn test_borrowed_value() {
+    use std::borrow::Cow;
+
+    let input = r#"{ "id": "bu", "name": "esc\"aped", "keys": [ 1, None ] }"#;
+    let value: BorrowedValue = from_str(input).unwrap();
+
+    let map = match &value {
+        BorrowedValue::Map(map) => map,
+        other => panic!("unexpected {:?}", other),
+    };
+
+    match &map[&BorrowedValue::String(Cow::Borrowed("id"))] {
+        BorrowedValue::String(Cow::Borrowed(s)) => {
+            assert_eq!("bu", *s);
+            assert!(input.as_bytes().as_ptr_range().contains(&s.as_ptr()));
+        }
+        other => panic!("expected a borrowed string, found {:?}", other),
+    }
+
+    match &map[&BorrowedValue::String(Cow::Borrowed("name"))] {
+        BorrowedValue::String(Cow::Owned(s)) => assert_eq!("esc\"aped", s),
+        other => panic!("expected an owned string, found {:?}", other),
+    }
+
+    assert_eq!(from_str::<Value>(input), Ok(value.into_owned()));
+}
+
+#[test]
+fn test_raw_value() {
+    use serde::Serialize;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Plugin<'a> {
+        name: String,
+        #[serde(borrow)]
+        config: &'a RawValue,
+        fallback: Option<Box<RawValue>>,
+    }
+
+    let source = r#"{
+    name: "clock",
+    config: Clock {
+        # shown in the bar
+        format: "%H:%M", # 24h
+        zones: [ 'a', ")", Some(1) ],
+    },
+    fallback: [1, 2],
+}"#;
+
+    let plugin: Plugin = from_str(source).unwrap();
+    assert_eq!(
+        r#"Clock {
+        # shown in the bar
+        format: "%H:%M", # 24h
+        zones: [ 'a', ")", Some(1) ],
+    }"#,
+        plugin.config.get_zmerald()
+    );
+    assert!(source.as_bytes().as_ptr_range().contains(&plugin.config.get_zmerald().as_ptr()));
+    assert_eq!("[1, 2]", plugin.fallback.as_ref().unwrap().get_zmerald());
+    assert_eq!(Ok(vec![1, 2]), plugin.fallback.as_ref().unwrap().into_rust::<Vec<u8>>());
+
+    assert_eq!(Ok(String::from(plugin.config.get_zmerald())), crate::ser::to_string(plugin.config));
+    assert_eq!(Ok(String::from("[[1, 2]]")), crate::ser::to_string(&vec![plugin.fallback.unwrap()]));
+
+    assert_eq!("bu", RawValue::from_zmerald("bu").unwrap().get_zmerald());
+    assert!(RawValue::from_zmerald("[1, 2").is_err());
+
+    // A `Value` has no source left, so it is written out again
+    let value: Value = from_str("[1, 2]").unwrap();
+    let raw: Box<RawValue> = value.into_rust().unwrap();
+    assert_eq!("[1,2]", raw.get_zmerald());
+}
+
+#[test]
+fn test_into_rust_error_path() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Key {
+        code: u16,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Keys {
+        keys: HashMap<u16, Vec<Key>>,
+    }
+
+    let value: Value = from_str(r#"{ "keys": { 49: [{ "code": 1 }, { "code": 1, "name": 2 }] } }"#).unwrap();
+    let error = value.to_rust_spanned::<Keys>().unwrap_err();
+    assert_eq!("keys.49[1]", error.path);
+    assert_eq!("Unexpected field named `name` in `Key`, expected `code` instead at `keys.49[1]`", error.to_string());
+
+    let error = value.clone().into_rust_spanned::<Keys>().unwrap_err();
+    assert!(matches!(error.code, Error::NoSuchStructField { .. }));
+    assert_eq!("keys.49[1]", error.path);
+
+    let error = value.into_rust::<Keys>().unwrap_err();
+    assert!(matches!(error, Error::AtPath { ref path, .. } if path == "keys.49[1]"));
+}
+
+#[test]
+fn test_map_index_mut() {
+    let mut map = Map::new();
+    map.insert(Value::String("a".into()), Value::Bool(true));
+
+    map[&Value::String("a".into())] = Value::Bool(false);
+    *map.entry(Value::String("b".into())).or_insert(Value::Unit) = Value::Char('b');
+
+    assert_eq!(Value::Bool(false), map[&Value::String("a".into())]);
+    assert_eq!(Some(&mut Value::Char('b')), map.get_mut(&Value::String("b".into())));
+    assert_eq!(None, map.get_mut(&Value::String("c".into())));
+    assert_eq!(2, map.len());
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key")]
+fn test_map_index_mut_missing_key() {
+    let mut map = Map::new();
+    map[&Value::String("c".into())] = Value::Unit;
+}
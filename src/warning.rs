@@ -0,0 +1,47 @@
+use crate::error::Span;
+use std::fmt;
+
+/// Something that reads fine but may not mean what its author meant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub span: Span,
+    /// Where in the document it is, written like the paths of a `SourceMap`.
+    pub path: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WarningCode {
+    /// A field the struct does not have, which was skipped.
+    UnknownField(String),
+    /// A map key written again, whose earlier value was overwritten.
+    DuplicateKey(String),
+    /// A value read as `Some` without being written as `Some(...)`.
+    ImplicitSome,
+    /// An integer too large for any integer type, read as a float instead.
+    LossyNumber,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span.start, self.code)?;
+
+        if !self.path.is_empty() {
+            write!(f, " at `{}`", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            WarningCode::UnknownField(ref field) => write!(f, "Unknown field `{}` is ignored", field),
+            WarningCode::DuplicateKey(ref key) => write!(f, "Duplicate key `{}` overwrites the earlier value", key),
+            WarningCode::ImplicitSome => f.write_str("Value is read as `Some` without being written as `Some(...)`"),
+            WarningCode::LossyNumber => f.write_str("Integer is too large and is read as a float, losing precision"),
+        }
+    }
+}